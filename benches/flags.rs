@@ -0,0 +1,38 @@
+// compares owned and borrowed flag parsing on a long command line
+// run with `cargo bench --bench flags`
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+use rsconfig::{quick::FlagConfig, quick::FlagView, CommandlineConfig};
+
+fn args() -> Vec<String> {
+    (0..100)
+        .map(|i| match i % 3 {
+            0 => format!("--flag{}", i),
+            1 => format!("--key{}={}", i, i),
+            _ => format!("input{}.txt", i),
+        })
+        .collect()
+}
+
+fn flag_parsing(c: &mut Criterion) {
+    let args = args();
+    let borrowed: Vec<&str> = args.iter().map(String::as_str).collect();
+
+    c.bench_function("FlagConfig::from_env_args", |b| {
+        b.iter(|| {
+            let flags = FlagConfig::from_env_args(black_box(args.clone()));
+            flags.contains("flag99")
+        })
+    });
+
+    c.bench_function("FlagView::parse", |b| {
+        b.iter(|| {
+            let flags = FlagView::parse(black_box(&borrowed).iter().copied());
+            flags.has("flag99")
+        })
+    });
+}
+
+criterion_group!(benches, flag_parsing);
+criterion_main!(benches);
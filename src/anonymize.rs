@@ -0,0 +1,419 @@
+use regex::{Captures, Regex};
+use serde_json::{Map, Number, Value};
+
+use std::{
+    collections::BTreeMap,
+    net::{Ipv4Addr, Ipv6Addr},
+    sync::OnceLock,
+};
+
+/// Rules for [`scrub`]. The defaults enable every built-in detector.
+#[derive(Debug, Clone)]
+pub struct ScrubRules {
+    /// Mixed into every placeholder. With a private salt, placeholders can't be matched against guessed originals.
+    pub salt: String,
+    /// Values of keys containing any of these (case-insensitive) are always replaced, whatever they look like.
+    pub secret_keys: Vec<String>,
+    /// Whether to replace hostnames like `db.internal.example.com`
+    pub hostnames: bool,
+    /// Whether to replace IPv4 and IPv6 addresses, except loopback and unspecified ones
+    pub ips: bool,
+    /// Whether to replace email addresses
+    pub emails: bool,
+    /// Whether to replace filesystem paths, one component at a time
+    pub paths: bool,
+    /// Extra patterns, each with the prefix used for its placeholders. Checked before the built-in detectors.
+    pub patterns: Vec<(String, Regex)>,
+}
+
+impl Default for ScrubRules {
+    fn default() -> Self {
+        Self {
+            salt: String::new(),
+            secret_keys: [
+                "password",
+                "passwd",
+                "secret",
+                "token",
+                "api_key",
+                "apikey",
+                "private_key",
+                "credential",
+            ]
+            .map(str::to_string)
+            .to_vec(),
+            hostnames: true,
+            ips: true,
+            emails: true,
+            paths: true,
+            patterns: Vec::new(),
+        }
+    }
+}
+
+impl ScrubRules {
+    /// Adds a pattern whose matches are replaced with placeholders starting with `prefix`.
+    pub fn pattern(mut self, prefix: &str, pattern: &str) -> Result<Self, regex::Error> {
+        self.patterns
+            .push((prefix.to_string(), Regex::new(pattern)?));
+        Ok(self)
+    }
+}
+
+/// The replacements made by [`scrub`], from original to placeholder.
+/// Keep it private: it's the only way back to the original values.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ScrubMap(BTreeMap<String, String>);
+
+impl ScrubMap {
+    /// Returns the placeholder an original value was replaced with.
+    pub fn get(&self, original: &str) -> Option<&str> {
+        self.0.get(original).map(String::as_str)
+    }
+
+    /// Iterates over the originals and their placeholders, sorted by original.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.0.iter().map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+
+    /// Returns the number of replaced values.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Returns true if nothing was replaced.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl Extend<(String, String)> for ScrubMap {
+    fn extend<I: IntoIterator<Item = (String, String)>>(&mut self, iter: I) {
+        self.0.extend(iter);
+    }
+}
+
+impl IntoIterator for ScrubMap {
+    type Item = (String, String);
+    type IntoIter = std::collections::btree_map::IntoIter<String, String>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+/// Replaces sensitive values in a config so it can be shared in bug reports.
+/// Replacements are deterministic: the same original (and salt) always gives the same placeholder,
+/// across runs and wherever it appears, including inside longer strings and in keys.
+/// Placeholders keep the shape of what they replace, so structure-dependent bugs still reproduce:
+/// - strings stay strings at least as long as the original, like `host-3fa2` for a short hostname
+/// - hostnames keep their dots, emails their `@`, and paths their separators and file extensions
+/// - IP addresses become private addresses of the same family
+/// - numbers under secret keys become numbers with the same number of digits; booleans and null are left alone
+/// ### Example
+/// ```rust
+/// use rsconfig::anonymize::{self, ScrubRules};
+///
+/// let config = serde_json::json!({
+///     "db": { "host": "db.example.com", "password": "hunter2" },
+///     "admin": "ops@example.com",
+/// });
+///
+/// let (scrubbed, map) = anonymize::scrub(&config, &ScrubRules::default());
+///
+/// assert_ne!(scrubbed["db"]["host"], "db.example.com");
+/// assert_eq!(map.get("hunter2"), scrubbed["db"]["password"].as_str());
+/// ```
+pub fn scrub(value: &Value, rules: &ScrubRules) -> (Value, ScrubMap) {
+    let mut scrubber = Scrubber {
+        rules,
+        map: ScrubMap::default(),
+    };
+
+    let value = scrubber.value(value, false);
+
+    (value, scrubber.map)
+}
+
+struct Scrubber<'a> {
+    rules: &'a ScrubRules,
+    map: ScrubMap,
+}
+
+impl Scrubber<'_> {
+    fn value(&mut self, value: &Value, secret: bool) -> Value {
+        match value {
+            Value::String(s) if secret => Value::String(self.placeholder("secret", s)),
+            Value::String(s) => Value::String(self.string(s)),
+            Value::Number(n) if secret => self.number(n),
+            Value::Array(values) => {
+                Value::Array(values.iter().map(|v| self.value(v, secret)).collect())
+            }
+            Value::Object(map) => {
+                let mut scrubbed = Map::new();
+
+                for (key, value) in map {
+                    let secret = secret || self.is_secret_key(key);
+                    let value = self.value(value, secret);
+                    scrubbed.insert(self.string(key), value);
+                }
+
+                Value::Object(scrubbed)
+            }
+            other => other.clone(),
+        }
+    }
+
+    fn is_secret_key(&self, key: &str) -> bool {
+        let key = key.to_lowercase();
+
+        self.rules
+            .secret_keys
+            .iter()
+            .any(|pattern| key.contains(&pattern.to_lowercase()))
+    }
+
+    fn string(&mut self, s: &str) -> String {
+        let mut s = s.to_string();
+
+        for (prefix, pattern) in &self.rules.patterns {
+            s = pattern
+                .replace_all(&s, |caps: &Captures| self.placeholder(prefix, &caps[0]))
+                .into_owned();
+        }
+
+        if self.rules.paths && looks_like_path(&s) {
+            return self.path(&s);
+        }
+
+        if self.rules.ips {
+            if let Ok(ip) = s.parse::<Ipv6Addr>() {
+                return self.ipv6(&s, ip);
+            }
+        }
+
+        // one pass over all detectors, so placeholders are never matched again
+        detectors()
+            .replace_all(&s, |caps: &Captures| {
+                let token = &caps[0];
+
+                if caps.name("email").is_some() && self.rules.emails {
+                    self.email(token)
+                } else if caps.name("ipv4").is_some() && self.rules.ips {
+                    match token.parse::<Ipv4Addr>() {
+                        Ok(ip) => self.ipv4(token, ip),
+                        Err(_) => token.to_string(),
+                    }
+                } else if caps.name("host").is_some() && self.rules.hostnames {
+                    self.hostname(token)
+                } else {
+                    token.to_string()
+                }
+            })
+            .into_owned()
+    }
+
+    fn placeholder(&mut self, prefix: &str, original: &str) -> String {
+        let len = original.chars().count().max(prefix.len() + 5);
+        let digits = hex_digits(&self.rules.salt, prefix, original, len - prefix.len() - 1);
+        let placeholder = format!("{}-{}", prefix, digits);
+
+        self.record(original, &placeholder);
+        placeholder
+    }
+
+    fn record(&mut self, original: &str, placeholder: &str) {
+        self.map
+            .0
+            .insert(original.to_string(), placeholder.to_string());
+    }
+
+    fn hostname(&mut self, host: &str) -> String {
+        let placeholder = host
+            .split('.')
+            .map(|label| self.placeholder("host", &label.to_lowercase()))
+            .collect::<Vec<_>>()
+            .join(".");
+
+        self.record(host, &placeholder);
+        placeholder
+    }
+
+    fn email(&mut self, email: &str) -> String {
+        let (user, domain) = email.split_once('@').unwrap_or((email, ""));
+        let placeholder = format!(
+            "{}@{}",
+            self.placeholder("user", user),
+            self.hostname(domain)
+        );
+
+        self.record(email, &placeholder);
+        placeholder
+    }
+
+    fn ipv4(&mut self, original: &str, ip: Ipv4Addr) -> String {
+        if ip.is_loopback() || ip.is_unspecified() {
+            return original.to_string();
+        }
+
+        let hash = fnv1a(&self.rules.salt, "ipv4", original).to_be_bytes();
+        let placeholder = Ipv4Addr::new(10, hash[0], hash[1], hash[2].max(1)).to_string();
+
+        self.record(original, &placeholder);
+        placeholder
+    }
+
+    fn ipv6(&mut self, original: &str, ip: Ipv6Addr) -> String {
+        if ip.is_loopback() || ip.is_unspecified() {
+            return original.to_string();
+        }
+
+        let hash = fnv1a(&self.rules.salt, "ipv6", original);
+        let segments = [
+            0xfd00,
+            (hash >> 48) as u16,
+            (hash >> 32) as u16,
+            0,
+            0,
+            0,
+            (hash >> 16) as u16,
+            (hash as u16).max(1),
+        ];
+        let placeholder = Ipv6Addr::from(segments).to_string();
+
+        self.record(original, &placeholder);
+        placeholder
+    }
+
+    fn path(&mut self, path: &str) -> String {
+        let mut placeholder = String::new();
+        let mut rest = path;
+
+        // drive letters and home directories aren't sensitive, and keep the path recognizable
+        if let Some(drive) = path.get(..2).filter(|d| d.ends_with(':')) {
+            placeholder.push_str(drive);
+            rest = &path[2..];
+        } else if let Some(after) = path.strip_prefix('~') {
+            placeholder.push('~');
+            rest = after;
+        }
+
+        // keep whichever separators the original used
+        let mut start = 0;
+        for (i, c) in rest.char_indices() {
+            if c == '/' || c == '\\' {
+                placeholder.push_str(&self.path_component(&rest[start..i]));
+                placeholder.push(c);
+                start = i + 1;
+            }
+        }
+        placeholder.push_str(&self.path_component(&rest[start..]));
+
+        self.record(path, &placeholder);
+        placeholder
+    }
+
+    fn path_component(&mut self, component: &str) -> String {
+        if component.is_empty() || component == "." || component == ".." {
+            return component.to_string();
+        }
+
+        match component
+            .rsplit_once('.')
+            .filter(|(stem, _)| !stem.is_empty())
+        {
+            Some((stem, extension)) => format!("{}.{}", self.placeholder("path", stem), extension),
+            None => self.placeholder("path", component),
+        }
+    }
+
+    fn number(&mut self, n: &Number) -> Value {
+        let original = n.to_string();
+        let hash = fnv1a(&self.rules.salt, "number", &original);
+
+        // keep the sign, the number of digits and whether it's an integer
+        let digits = original
+            .trim_start_matches('-')
+            .split('.')
+            .next()
+            .unwrap_or("")
+            .len() as u32;
+        let low = if digits <= 1 {
+            0
+        } else {
+            10u64.pow(digits.min(18) - 1)
+        };
+        let high = 10u64.pow(digits.min(18));
+        let magnitude = low + hash % (high - low);
+
+        let placeholder = match (n.is_f64(), original.starts_with('-')) {
+            (true, negative) => {
+                let float = magnitude as f64 + 0.5;
+                Number::from_f64(if negative { -float } else { float })
+            }
+            (false, true) => Some(Number::from(-(magnitude as i64))),
+            (false, false) => Some(Number::from(magnitude)),
+        };
+
+        match placeholder {
+            Some(placeholder) => {
+                self.record(&original, &placeholder.to_string());
+                Value::Number(placeholder)
+            }
+            None => Value::Number(n.clone()),
+        }
+    }
+}
+
+fn looks_like_path(s: &str) -> bool {
+    let bytes = s.as_bytes();
+
+    (s.starts_with('/') && s.len() > 1 && !s.starts_with("//"))
+        || s.starts_with("~/")
+        || s.starts_with("./")
+        || s.starts_with("../")
+        || (bytes.len() > 2
+            && bytes[0].is_ascii_alphabetic()
+            && bytes[1] == b':'
+            && matches!(bytes[2], b'\\' | b'/'))
+}
+
+fn detectors() -> &'static Regex {
+    static DETECTORS: OnceLock<Regex> = OnceLock::new();
+
+    DETECTORS.get_or_init(|| {
+        Regex::new(concat!(
+            r"(?i)(?P<email>[a-z0-9._%+-]+@(?:[a-z0-9-]+\.)+[a-z]{2,})",
+            r"|\b(?P<ipv4>(?:\d{1,3}\.){3}\d{1,3})\b",
+            r"|\b(?P<host>(?:[a-z0-9](?:[a-z0-9-]*[a-z0-9])?\.)+[a-z][a-z0-9-]*[a-z0-9])\b",
+        ))
+        .unwrap()
+    })
+}
+
+// FNV-1a, implemented here so placeholders don't change between Rust versions like std's hashers may
+fn fnv1a(salt: &str, kind: &str, original: &str) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+
+    for part in [salt, kind, original] {
+        for byte in part.bytes().chain([0]) {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+    }
+
+    hash
+}
+
+fn hex_digits(salt: &str, kind: &str, original: &str, len: usize) -> String {
+    let mut digits = String::with_capacity(len + 16);
+    let mut hash = fnv1a(salt, kind, original);
+
+    while digits.len() < len {
+        digits.push_str(&format!("{:016x}", hash));
+        hash = fnv1a(salt, kind, &hash.to_string());
+    }
+
+    digits.truncate(len);
+    digits
+}
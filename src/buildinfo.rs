@@ -0,0 +1,197 @@
+use serde_json::{json, Value};
+
+use std::{
+    env, fmt,
+    path::Path,
+    process::Command,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+const VERSION: &str = "RSCONFIG_BUILD_VERSION";
+const GIT_HASH: &str = "RSCONFIG_BUILD_GIT_HASH";
+const FEATURES: &str = "RSCONFIG_BUILD_FEATURES";
+const TARGET: &str = "RSCONFIG_BUILD_TARGET";
+const TIMESTAMP: &str = "RSCONFIG_BUILD_TIMESTAMP";
+
+/// Describes how the running binary was built, so it can be reported next to the effective config.
+/// Fields that couldn't be determined at build time are left empty.
+/// ### Example
+/// In `build.rs` (with rsconfig as a build-dependency using the `buildinfo` feature):
+/// ```rust,no_run
+/// // build.rs
+/// rsconfig::buildinfo::emit();
+/// ```
+/// Then anywhere in the crate:
+/// ```rust
+/// let info = rsconfig::build_info!();
+/// println!("{}", info);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BuildInfo {
+    /// Version of the crate that was built
+    pub version: String,
+    /// Short git commit hash, if the crate was built from a git checkout
+    pub git_hash: String,
+    /// Enabled cargo features, sorted, see [`emit`] for how they're named
+    pub features: Vec<String>,
+    /// Target triple the binary was built for
+    pub target: String,
+    /// Build time in seconds since the unix epoch
+    pub timestamp: Option<u64>,
+}
+
+impl BuildInfo {
+    /// Builds the info from variables written by [`emit`], looked up with the given function.
+    /// [`build_info!`](crate::build_info) calls this with the values captured at compile time.
+    pub fn from_vars(lookup: impl Fn(&str) -> Option<String>) -> Self {
+        let var = |name| lookup(name).unwrap_or_default();
+
+        Self {
+            version: var(VERSION),
+            git_hash: var(GIT_HASH),
+            features: var(FEATURES)
+                .split(',')
+                .filter(|f| !f.is_empty())
+                .map(str::to_string)
+                .collect(),
+            target: var(TARGET),
+            timestamp: var(TIMESTAMP).parse().ok(),
+        }
+    }
+
+    /// Converts the info to JSON, for embedding next to the config in reports.
+    pub fn to_json(&self) -> Value {
+        json!({
+            "version": self.version,
+            "git_hash": self.git_hash,
+            "features": self.features,
+            "target": self.target,
+            "timestamp": self.timestamp,
+        })
+    }
+}
+
+impl fmt::Display for BuildInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let or_unknown = |s: &str| if s.is_empty() { "unknown" } else { s }.to_string();
+
+        write!(f, "version {}", or_unknown(&self.version))?;
+        write!(f, " (git {})", or_unknown(&self.git_hash))?;
+        write!(f, " target {}", or_unknown(&self.target))?;
+        write!(f, " features [{}]", self.features.join(", "))?;
+
+        match self.timestamp {
+            Some(timestamp) => write!(f, " built at {}", timestamp),
+            None => write!(f, " built at unknown"),
+        }
+    }
+}
+
+/// Captures the build information of the crate being compiled. Call this from `build.rs`.
+/// Writes `cargo:rustc-env` lines that [`build_info!`](crate::build_info) reads back at compile time.
+/// Missing git or cargo variables leave the matching fields empty instead of failing the build.
+/// The timestamp honours `SOURCE_DATE_EPOCH` for reproducible builds.
+///
+/// The build script is rerun when the checked out commit or `SOURCE_DATE_EPOCH` changes, so the timestamp
+/// is the time of the first build of a commit rather than of every build.
+///
+/// Feature names are read from `CARGO_CFG_FEATURE`, which newer versions of Cargo set. Older versions only set
+/// `CARGO_FEATURE_<NAME>`, upper-cased with `-` turned into `_`, so the names are guessed from those as lower-case
+/// with `-`: a feature named `my_feature` or `My-Feature` is reported as `my-feature`.
+pub fn emit() {
+    let version = env::var("CARGO_PKG_VERSION").unwrap_or_default();
+    let target = env::var("TARGET").unwrap_or_default();
+    let features = feature_names(env::vars());
+
+    let git_hash = git(&["rev-parse", "--short", "HEAD"]).unwrap_or_default();
+
+    // watching any file turns off Cargo's default of rerunning on every change in the package,
+    // so the commit has to be watched for the hash to stay current
+    let mut watched = vec!["HEAD".to_string(), "packed-refs".to_string()];
+    watched.extend(git(&["symbolic-ref", "-q", "HEAD"]));
+
+    for name in watched {
+        if let Some(path) = git(&["rev-parse", "--git-path", &name]) {
+            // a missing file would rerun the script on every build
+            if Path::new(&path).exists() {
+                println!("cargo:rerun-if-changed={}", path);
+            }
+        }
+    }
+
+    let timestamp = env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|epoch| epoch.parse::<u64>().ok())
+        .or_else(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .ok()
+                .map(|d| d.as_secs())
+        })
+        .map(|t| t.to_string())
+        .unwrap_or_default();
+
+    println!("cargo:rerun-if-env-changed=SOURCE_DATE_EPOCH");
+    println!("cargo:rustc-env={}={}", VERSION, version);
+    println!("cargo:rustc-env={}={}", GIT_HASH, git_hash);
+    println!("cargo:rustc-env={}={}", FEATURES, features.join(","));
+    println!("cargo:rustc-env={}={}", TARGET, target);
+    println!("cargo:rustc-env={}={}", TIMESTAMP, timestamp);
+}
+
+// the enabled features, from the build script's environment variables
+pub(crate) fn feature_names(vars: impl Iterator<Item = (String, String)>) -> Vec<String> {
+    let mut guessed = Vec::new();
+
+    for (name, value) in vars {
+        if name == "CARGO_CFG_FEATURE" {
+            let mut features: Vec<String> = value
+                .split(',')
+                .filter(|f| !f.is_empty())
+                .map(str::to_string)
+                .collect();
+            features.sort();
+
+            return features;
+        }
+
+        if let Some(feature) = name.strip_prefix("CARGO_FEATURE_") {
+            guessed.push(feature.to_lowercase().replace('_', "-"));
+        }
+    }
+
+    guessed.sort();
+    guessed
+}
+
+// the trimmed output of a git command, or None if git isn't there or the command fails
+fn git(args: &[&str]) -> Option<String> {
+    Command::new("git")
+        .args(args)
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .map(|output| output.trim().to_string())
+        .filter(|output| !output.is_empty())
+}
+
+/// Creates a [`BuildInfo`](crate::buildinfo::BuildInfo) from the variables written by
+/// [`buildinfo::emit`](crate::buildinfo::emit) in the calling crate's build script.
+/// Fields are empty if the build script didn't run `emit`.
+#[macro_export]
+macro_rules! build_info {
+    () => {
+        $crate::buildinfo::BuildInfo::from_vars(|name| {
+            match name {
+                "RSCONFIG_BUILD_VERSION" => option_env!("RSCONFIG_BUILD_VERSION"),
+                "RSCONFIG_BUILD_GIT_HASH" => option_env!("RSCONFIG_BUILD_GIT_HASH"),
+                "RSCONFIG_BUILD_FEATURES" => option_env!("RSCONFIG_BUILD_FEATURES"),
+                "RSCONFIG_BUILD_TARGET" => option_env!("RSCONFIG_BUILD_TARGET"),
+                "RSCONFIG_BUILD_TIMESTAMP" => option_env!("RSCONFIG_BUILD_TIMESTAMP"),
+                _ => None,
+            }
+            .map(str::to_string)
+        })
+    };
+}
@@ -0,0 +1,63 @@
+use ciborium::Value;
+
+use std::io;
+
+/// Encodes a CBOR value canonically, so the same config always produces the same bytes and hashing saved files is stable.
+/// Follows the core deterministic encoding rules of RFC 8949:
+/// - integers, lengths and floats use their shortest form
+/// - arrays, maps, byte strings and text strings use definite lengths
+/// - map entries are sorted by the bytewise order of their encoded keys, at every level
+/// ### Example
+/// ```rust
+/// use ciborium::Value;
+///
+/// let a = Value::Map(vec![("b".into(), 1.into()), ("a".into(), 2.into())]);
+/// let b = Value::Map(vec![("a".into(), 2.into()), ("b".into(), 1.into())]);
+///
+/// assert_eq!(
+///     rsconfig::cbor::to_canonical_vec(&a).unwrap(),
+///     rsconfig::cbor::to_canonical_vec(&b).unwrap(),
+/// );
+/// ```
+pub fn to_canonical_vec(val: &Value) -> io::Result<Vec<u8>> {
+    let mut val = val.clone();
+    canonicalize(&mut val)?;
+
+    // ciborium already writes shortest forms and definite lengths, only the key order is left
+    encode(&val)
+}
+
+fn canonicalize(val: &mut Value) -> io::Result<()> {
+    match val {
+        Value::Array(values) => {
+            for value in values {
+                canonicalize(value)?;
+            }
+        }
+        Value::Map(entries) => {
+            let mut keyed = Vec::with_capacity(entries.len());
+
+            for (mut key, mut value) in entries.drain(..) {
+                canonicalize(&mut key)?;
+                canonicalize(&mut value)?;
+                keyed.push((encode(&key)?, key, value));
+            }
+
+            keyed.sort_by(|(a, _, _), (b, _, _)| a.cmp(b));
+            entries.extend(keyed.into_iter().map(|(_, key, value)| (key, value)));
+        }
+        Value::Tag(_, inner) => canonicalize(inner)?,
+        _ => {}
+    }
+
+    Ok(())
+}
+
+fn encode(val: &Value) -> io::Result<Vec<u8>> {
+    let mut data = Vec::new();
+
+    ciborium::into_writer(val, &mut data)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e.to_string()))?;
+
+    Ok(data)
+}
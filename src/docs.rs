@@ -0,0 +1,165 @@
+use crate::{files, ConfigError};
+
+use std::{collections::HashMap, path::Path};
+
+/// Options for [`harvest_comments_with`].
+#[derive(Debug, Clone, Default)]
+pub struct HarvestOptions {
+    /// Whether a comment block separated from the next key by blank lines still documents that key.
+    /// When false (the default), such blocks are treated as orphans and dropped.
+    pub attach_across_blank_lines: bool,
+}
+
+/// Collects the comments in a YAML (YML) file, keyed by the dotted path of the key they document.
+/// A block of comment lines documents the key that immediately follows it,
+/// and a trailing comment on a key's line is appended to that key's documentation.
+/// Comments that don't precede a key (such as the end of a file or before a list item) are ignored.
+/// ### Example
+/// ```yaml
+/// # port the server listens on
+/// server:
+///   port: 8080 # must be free
+/// ```
+/// Produces `server` → `port the server listens on` and `server.port` → `must be free`.
+/// Returns a [`ConfigError`] naming the file if it can't be read.
+pub fn harvest_comments(path: impl AsRef<Path>) -> Result<HashMap<String, String>, ConfigError> {
+    harvest_comments_with(path, &HarvestOptions::default())
+}
+
+/// Same as [`harvest_comments`], with control over how comment blocks are associated with keys.
+pub fn harvest_comments_with(
+    path: impl AsRef<Path>,
+    options: &HarvestOptions,
+) -> Result<HashMap<String, String>, ConfigError> {
+    // read like the loaders, so a byte order mark doesn't hide the first comment
+    let data = files::read_to_string(path.as_ref())?;
+
+    Ok(harvest(&data, options))
+}
+
+fn harvest(data: &str, options: &HarvestOptions) -> HashMap<String, String> {
+    let mut docs = HashMap::new();
+
+    // keys enclosing the current line, with their indentation
+    let mut parents: Vec<(usize, String)> = Vec::new();
+    let mut pending: Vec<&str> = Vec::new();
+
+    // indentation of a key whose block scalar is being skipped
+    let mut block_indent: Option<usize> = None;
+
+    for line in data.lines() {
+        let content = line.trim_start();
+        let indent = line.len() - content.len();
+
+        if let Some(key_indent) = block_indent {
+            if content.is_empty() || indent > key_indent {
+                continue;
+            }
+
+            block_indent = None;
+        }
+
+        if content.is_empty() {
+            if !options.attach_across_blank_lines {
+                pending.clear();
+            }
+
+            continue;
+        }
+
+        if let Some(comment) = content.strip_prefix('#') {
+            pending.push(comment.strip_prefix(' ').unwrap_or(comment).trim_end());
+            continue;
+        }
+
+        if content.starts_with("---") || content.starts_with("...") {
+            parents.clear();
+            pending.clear();
+            continue;
+        }
+
+        let Some((key, rest)) = split_key(content) else {
+            // list items, flow collections and continuation lines don't get docs
+            pending.clear();
+            continue;
+        };
+
+        while parents.last().is_some_and(|(i, _)| *i >= indent) {
+            parents.pop();
+        }
+
+        let mut path: Vec<&str> = parents.iter().map(|(_, k)| k.as_str()).collect();
+        path.push(&key);
+        let path = path.join(".");
+
+        let (value, trailing) = split_trailing_comment(rest);
+
+        let mut doc = pending.join("\n");
+        if let Some(trailing) = trailing {
+            if !doc.is_empty() {
+                doc.push('\n');
+            }
+            doc.push_str(trailing);
+        }
+
+        if !doc.is_empty() {
+            docs.insert(path, doc);
+        }
+        pending.clear();
+
+        if value.starts_with('|') || value.starts_with('>') {
+            block_indent = Some(indent);
+        }
+
+        parents.push((indent, key));
+    }
+
+    docs
+}
+
+// splits `key: rest` into the unquoted key and everything after the colon
+fn split_key(content: &str) -> Option<(String, &str)> {
+    if let Some(quote) = content.chars().next().filter(|c| *c == '"' || *c == '\'') {
+        let end = content[1..].find(quote)? + 1;
+        let rest = content[end + 1..].strip_prefix(':')?;
+
+        if !rest.is_empty() && !rest.starts_with([' ', '\t']) {
+            return None;
+        }
+
+        return Some((content[1..end].to_string(), rest));
+    }
+
+    if content.starts_with(['-', '[', '{', '?', '&', '*', '!', '|', '>']) {
+        return None;
+    }
+
+    let end = content
+        .match_indices(':')
+        .map(|(i, _)| i)
+        .find(|i| matches!(content.as_bytes().get(i + 1), None | Some(b' ' | b'\t')))?;
+
+    Some((content[..end].trim_end().to_string(), &content[end + 1..]))
+}
+
+// splits a value from an inline ` # comment`, ignoring `#` inside quotes
+fn split_trailing_comment(rest: &str) -> (&str, Option<&str>) {
+    let mut quote = None;
+    let mut prev = ' ';
+
+    for (i, c) in rest.char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(q), _) if c == q => quote = None,
+            (None, '#') if prev == ' ' || prev == '\t' => {
+                let comment = rest[i + 1..].trim();
+                return (rest[..i].trim(), Some(comment).filter(|c| !c.is_empty()));
+            }
+            _ => {}
+        }
+
+        prev = c;
+    }
+
+    (rest.trim(), None)
+}
@@ -0,0 +1,175 @@
+use crate::*;
+
+use serde_json::{Map, Number};
+
+use std::{error::Error, ffi::OsString, fmt};
+
+/// Error returned when prefixed environment variables can't be turned into a config.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EnvError {
+    /// Name of the variable that caused the error
+    pub variable: String,
+    /// Description of what was wrong with the variable
+    pub message: String,
+}
+
+impl fmt::Display for EnvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.variable, self.message)
+    }
+}
+
+impl Error for EnvError {}
+
+/// Loads a configuration struct from the environment variables starting with `prefix`, through `JsonConfig::from_json`.
+/// See [`prefixed_value`] for how the variables are mapped.
+/// Output type must impl JsonConfig
+/// ### Example
+/// ```rust,no_run
+/// # use rsconfig::{env, JsonConfig};
+/// # fn example<T: JsonConfig>() -> Result<(), env::EnvError> {
+/// // MYAPP_DEBUG=true MYAPP_DB__HOST=localhost MYAPP_DB__PORT=5432
+/// let config: T = env::load_prefixed("MYAPP_")?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn load_prefixed<T: JsonConfig>(prefix: &str) -> Result<T, EnvError> {
+    prefixed_value(prefix).map(T::from_json)
+}
+
+/// Collects the environment variables starting with `prefix` into a JSON object.
+/// Same as [`prefixed_value_from`], reading the process's environment.
+pub fn prefixed_value(prefix: &str) -> Result<Value, EnvError> {
+    prefixed_value_from(std::env::vars_os(), prefix)
+}
+
+/// Collects the variables starting with `prefix` into a JSON object, for reuse with `JsonConfig::from_json`.
+/// - the prefix is stripped and the rest of the name is lowercased, so `MYAPP_LOG_LEVEL` becomes `log_level`
+/// - a double underscore nests objects, so `MYAPP_DB__HOST` becomes `{"db": {"host": ...}}`
+/// - `true` and `false` become booleans and integers become numbers
+/// - values with a comma become a list, with each item converted the same way
+///
+/// Everything else is kept as a string. Variables that aren't valid UTF-8, have an empty name segment,
+/// hold an integer too large to represent or clash with another variable (like `MYAPP_DB` and `MYAPP_DB__HOST`)
+/// are an error naming the variable.
+/// ### Example
+/// ```rust
+/// use rsconfig::env;
+///
+/// let vars = [("MYAPP_DB__PORT", "5432"), ("MYAPP_HOSTS", "a,b"), ("OTHER", "ignored")];
+/// let value = env::prefixed_value_from(vars.map(|(k, v)| (k.into(), v.into())), "MYAPP_").unwrap();
+///
+/// assert_eq!(value, serde_json::json!({"db": {"port": 5432}, "hosts": ["a", "b"]}));
+/// ```
+pub fn prefixed_value_from(
+    vars: impl IntoIterator<Item = (OsString, OsString)>,
+    prefix: &str,
+) -> Result<Value, EnvError> {
+    let mut vars: Vec<(String, OsString)> = vars
+        .into_iter()
+        .map(|(name, value)| (name.to_string_lossy().into_owned(), value))
+        .filter(|(name, _)| name.starts_with(prefix))
+        .collect();
+
+    // the environment has no order, sorting keeps clashes reported the same way every time
+    vars.sort();
+
+    let mut root = Map::new();
+
+    for (variable, value) in vars {
+        let error = |message: &str| EnvError {
+            variable: variable.clone(),
+            message: message.to_string(),
+        };
+
+        let value = value
+            .to_str()
+            .ok_or_else(|| error("value isn't valid UTF-8"))
+            .and_then(|value| coerce(value).map_err(error))?;
+
+        let key = variable[prefix.len()..].to_lowercase();
+        let segments: Vec<&str> = key.split("__").collect();
+
+        if segments.iter().any(|segment| segment.is_empty()) {
+            return Err(error("name has an empty segment after the prefix"));
+        }
+
+        // names only differing in case end up on the same key
+        if insert(&mut root, &segments, value)
+            .map_err(|message| error(&message))?
+            .is_some()
+        {
+            return Err(error(&format!("`{}` is set more than once", key)));
+        }
+    }
+
+    Ok(Value::Object(root))
+}
+
+// sets the value at a path of keys, creating the objects along the way and returning the value it replaced
+pub(crate) fn insert(
+    root: &mut Map<String, Value>,
+    segments: &[&str],
+    value: Value,
+) -> Result<Option<Value>, String> {
+    let (last, parents) = segments
+        .split_last()
+        .expect("a key has at least one segment");
+    let mut map = root;
+
+    for segment in parents {
+        let entry = map
+            .entry(segment.to_string())
+            .or_insert_with(|| Value::Object(Map::new()));
+
+        map = match entry {
+            Value::Object(child) => child,
+            _ => {
+                return Err(format!(
+                    "`{}` is already set to a value, it can't also have keys",
+                    segment
+                ))
+            }
+        };
+    }
+
+    if let Some(Value::Object(_)) = map.get(*last) {
+        return Err(format!(
+            "`{}` already has keys, it can't also be set to a value",
+            last
+        ));
+    }
+
+    Ok(map.insert(last.to_string(), value))
+}
+
+pub(crate) fn coerce(value: &str) -> Result<Value, &'static str> {
+    if value.contains(',') {
+        return value
+            .split(',')
+            .map(|item| coerce_scalar(item.trim()))
+            .collect();
+    }
+
+    coerce_scalar(value)
+}
+
+fn coerce_scalar(value: &str) -> Result<Value, &'static str> {
+    match value {
+        "true" => return Ok(Value::Bool(true)),
+        "false" => return Ok(Value::Bool(false)),
+        _ => {}
+    }
+
+    let digits = value.strip_prefix('-').unwrap_or(value);
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return Ok(Value::String(value.to_string()));
+    }
+
+    let number = match value.parse::<i64>() {
+        Ok(n) => Number::from(n),
+        Err(_) => Number::from(value.parse::<u64>().map_err(|_| "integer is too large")?),
+    };
+
+    Ok(Value::Number(number))
+}
@@ -0,0 +1,311 @@
+use std::{
+    error::Error,
+    fmt, io,
+    path::{Path, PathBuf},
+};
+
+/// Error returned when a config file can't be loaded or saved. Every variant carries the path of the offending file (or files),
+/// which is empty for data that didn't come from a file, like `files::load_from_yaml_str`.
+/// ### Example
+/// ```rust
+/// use rsconfig::{files, ConfigError, YamlConfig};
+/// # use std::io::Result;
+/// # struct T;
+/// # impl YamlConfig for T {
+/// #     fn from_yaml(yaml: Vec<yaml_rust::Yaml>) -> Self { T }
+/// #     fn save_yaml(&self, path: &str) -> Result<()> { Ok(()) }
+/// # }
+///
+/// match files::try_load_from_yaml::<T>("missing.yml") {
+///     Err(ConfigError::Io { path, source }) => println!("can't read {}: {}", path.display(), source),
+///     Err(e) => println!("{}", e),
+///     Ok(_) => {}
+/// }
+/// ```
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum ConfigError {
+    /// The file couldn't be read
+    Io {
+        /// Path of the file
+        path: PathBuf,
+        /// The underlying error
+        source: io::Error,
+    },
+    /// The file couldn't be written
+    Write {
+        /// Path of the file
+        path: PathBuf,
+        /// The underlying error
+        source: io::Error,
+    },
+    /// The file isn't valid YAML
+    YamlParse {
+        /// Path of the file
+        path: PathBuf,
+        /// The underlying error, including the line and column
+        source: yaml_rust::ScanError,
+    },
+    /// The file isn't valid JSON
+    JsonParse {
+        /// Path of the file
+        path: PathBuf,
+        /// The underlying error, including the line and column
+        source: serde_json::Error,
+    },
+    /// The file isn't valid in one of the other formats, like TOML or INI
+    Parse {
+        /// Path of the file
+        path: PathBuf,
+        /// Name of the format the file was parsed as
+        format: &'static str,
+        /// The underlying error
+        source: Box<dyn Error + Send + Sync>,
+    },
+    /// `files::load_from_file` doesn't recognize the file's extension
+    UnsupportedExtension {
+        /// Path of the file
+        path: PathBuf,
+        /// The extension that was seen
+        extension: String,
+        /// The extensions `files::load_from_file` recognizes, with the enabled features
+        supported: Vec<&'static str>,
+    },
+    /// The file has no extension (or an empty one), so `files::load_from_file` can't tell its format
+    NoExtension {
+        /// Path of the file
+        path: PathBuf,
+        /// The extensions `files::load_from_file` recognizes, with the enabled features
+        supported: Vec<&'static str>,
+    },
+    /// The config type doesn't opt into the file's format through its `FileConfig::try_from_*` method,
+    /// or `files::save_to_file` can't write the format
+    UnsupportedFormat {
+        /// Path of the file
+        path: PathBuf,
+        /// Name of the format the file is in
+        format: &'static str,
+    },
+    /// Another process holds the file's lock, returned when locking with `LockMode::Try`
+    #[cfg(feature = "locking")]
+    Locked {
+        /// Path of the file
+        path: PathBuf,
+    },
+    /// None of the files `files::load_from_first` or `files::load_merged` tried exist
+    NoneFound {
+        /// Every path that was tried, in order
+        paths: Vec<PathBuf>,
+    },
+    /// `files::load_with_detection` couldn't tell the file's format from its contents
+    UnknownFormat {
+        /// Path of the file
+        path: PathBuf,
+        /// Each format that was tried, with the reason it didn't match
+        failures: Vec<(&'static str, Box<dyn Error + Send + Sync>)>,
+    },
+    /// A commandline flag that names the config file, like `--config`, was given without a path
+    MissingArgValue {
+        /// The flag, like `--config`
+        flag: String,
+    },
+    /// The file has a `${VAR}` placeholder for a variable that isn't set and has no default,
+    /// returned when loading with `LoadOptions::expand_env`
+    UnsetVariable {
+        /// Path of the file
+        path: PathBuf,
+        /// Name of the variable
+        variable: String,
+    },
+}
+
+impl ConfigError {
+    /// The path of the file that couldn't be loaded.
+    /// For [`ConfigError::NoneFound`] it's the first path that was tried, or empty if there were none.
+    /// [`ConfigError::MissingArgValue`] has no path, so it's empty.
+    pub fn path(&self) -> &Path {
+        match self {
+            Self::NoneFound { paths } => paths.first().map_or(Path::new(""), PathBuf::as_path),
+            Self::MissingArgValue { .. } => Path::new(""),
+            Self::Io { path, .. }
+            | Self::Write { path, .. }
+            | Self::YamlParse { path, .. }
+            | Self::JsonParse { path, .. }
+            | Self::Parse { path, .. }
+            | Self::UnsupportedExtension { path, .. }
+            | Self::NoExtension { path, .. }
+            | Self::UnsupportedFormat { path, .. }
+            | Self::UnknownFormat { path, .. }
+            | Self::UnsetVariable { path, .. } => path,
+            #[cfg(feature = "locking")]
+            Self::Locked { path } => path,
+        }
+    }
+
+    /// The line and column (both starting from 1) a YAML or JSON parse error was found at.
+    pub fn location(&self) -> Option<(usize, usize)> {
+        match self {
+            Self::YamlParse { source, .. } => {
+                Some((source.marker().line(), source.marker().col() + 1))
+            }
+            Self::JsonParse { source, .. } => Some((source.line(), source.column())),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn io(path: impl Into<PathBuf>, source: io::Error) -> Self {
+        Self::Io {
+            path: path.into(),
+            source,
+        }
+    }
+
+    pub(crate) fn write(path: impl Into<PathBuf>, source: io::Error) -> Self {
+        Self::Write {
+            path: path.into(),
+            source,
+        }
+    }
+
+    pub(crate) fn parse(
+        path: impl Into<PathBuf>,
+        format: &'static str,
+        source: impl Into<Box<dyn Error + Send + Sync>>,
+    ) -> Self {
+        Self::Parse {
+            path: path.into(),
+            format,
+            source: source.into(),
+        }
+    }
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io { path, source } if path.as_os_str().is_empty() => {
+                write!(f, "Failed to read config data: {}", source)
+            }
+            Self::Io { path, source } => write!(f, "Failed to read {}: {}", path.display(), source),
+            Self::Write { path, source } => write!(f, "Failed to write {}: {}", path.display(), source),
+            Self::YamlParse { path, source } if path.as_os_str().is_empty() => {
+                write!(f, "Failed to parse YAML: {}", source)
+            }
+            Self::YamlParse { path, source } => {
+                write!(f, "Failed to parse YAML in {}: {}", path.display(), source)
+            }
+            Self::JsonParse { path, source } if path.as_os_str().is_empty() => {
+                write!(f, "Failed to parse JSON: {}", source)
+            }
+            Self::JsonParse { path, source } => {
+                write!(f, "Failed to parse JSON in {}: {}", path.display(), source)
+            }
+            Self::Parse {
+                path,
+                format,
+                source,
+            } if path.as_os_str().is_empty() => write!(f, "Failed to parse {}: {}", format, source),
+            Self::Parse {
+                path,
+                format,
+                source,
+            } => write!(f, "Failed to parse {} in {}: {}", format, path.display(), source),
+            Self::UnsupportedExtension {
+                path,
+                extension,
+                supported,
+            } => write!(
+                f,
+                "Unsupported extension `.{}` for {}, expected one of: {}",
+                extension,
+                path.display(),
+                supported.join(", ")
+            ),
+            Self::NoExtension { path, supported } => write!(
+                f,
+                "{} has no extension to tell its format by, expected one of: {}",
+                path.display(),
+                supported.join(", ")
+            ),
+            Self::UnsupportedFormat { path, format } => write!(
+                f,
+                "{} is {}, which this config type doesn't support",
+                path.display(),
+                format
+            ),
+            #[cfg(feature = "locking")]
+            Self::Locked { path } => write!(
+                f,
+                "{} is locked by another process, try again later",
+                path.display()
+            ),
+            Self::NoneFound { paths } if paths.is_empty() => {
+                write!(f, "No config file found, there were no paths to try")
+            }
+            Self::NoneFound { paths } => {
+                write!(f, "No config file found, tried: ")?;
+
+                for (i, path) in paths.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", path.display())?;
+                }
+
+                Ok(())
+            }
+            Self::UnknownFormat { path, failures } => {
+                write!(f, "Couldn't detect the format of {}", path.display())?;
+
+                for (format, reason) in failures {
+                    write!(f, "; not {}: {}", format, reason)?;
+                }
+
+                Ok(())
+            }
+            Self::MissingArgValue { flag } => write!(
+                f,
+                "{} needs a path to the config file, like `{} config.yml` or `{}=config.yml`",
+                flag, flag, flag
+            ),
+            Self::UnsetVariable { path, variable } => write!(
+                f,
+                "{} uses the environment variable {}, which isn't set, set it or give a default with `${{{}:-default}}`",
+                path.display(),
+                variable,
+                variable
+            ),
+        }
+    }
+}
+
+impl Error for ConfigError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io { source, .. } | Self::Write { source, .. } => Some(source),
+            Self::YamlParse { source, .. } => Some(source),
+            Self::JsonParse { source, .. } => Some(source),
+            Self::Parse { source, .. } => Some(source.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+// lets the loaders that return io::Result share the fallible internals
+impl From<ConfigError> for io::Error {
+    fn from(e: ConfigError) -> Self {
+        let kind = match &e {
+            ConfigError::Io { source, .. } | ConfigError::Write { source, .. } => source.kind(),
+            ConfigError::NoneFound { .. } => io::ErrorKind::NotFound,
+            ConfigError::MissingArgValue { .. } => io::ErrorKind::InvalidInput,
+            ConfigError::UnsupportedExtension { .. }
+            | ConfigError::NoExtension { .. }
+            | ConfigError::UnsupportedFormat { .. } => io::ErrorKind::Unsupported,
+            #[cfg(feature = "locking")]
+            ConfigError::Locked { .. } => io::ErrorKind::WouldBlock,
+            _ => io::ErrorKind::InvalidData,
+        };
+
+        io::Error::new(kind, e)
+    }
+}
@@ -3,34 +3,2190 @@ use crate::*;
 use serde_json::Value;
 use yaml_rust::YamlLoader;
 
-use std::fs;
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    env,
+    error::Error,
+    ffi::OsString,
+    fmt,
+    fs::{self, File},
+    io::{self, Read},
+    path::{Path, PathBuf},
+    process,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+#[cfg(unix)]
+use std::os::fd::{AsFd, AsRawFd};
+#[cfg(windows)]
+use std::os::windows::io::{AsHandle, AsRawHandle};
+
+/// Represents the file formats that configs can be loaded from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigFormat {
+    /// YAML (YML), loaded through YamlConfig
+    Yaml,
+    /// JSON, loaded through JsonConfig
+    Json,
+}
 
 /// Loads a configuration struct from a YAML (YML) file.
+/// Panics if the file can't be read or parsed, use [`try_load_from_yaml`] to handle the error instead.
+/// Output type must impl YamlConfig
+pub fn load_from_yaml<T: YamlConfig>(path: impl AsRef<Path>) -> T {
+    try_load_from_yaml(path).unwrap_or_else(|e| panic!("{}", e))
+}
+
+/// Loads a configuration struct from a YAML (YML) file, returning an error if it can't be read or parsed.
 /// Output type must impl YamlConfig
-pub fn load_from_yaml<T: YamlConfig>(path: &str) -> T {
-    let data = fs::read_to_string(path).expect("Failed to read file");
-    let yaml = YamlLoader::load_from_str(&data).expect("Failed to parse YAML");
+pub fn try_load_from_yaml<T: YamlConfig>(path: impl AsRef<Path>) -> Result<T, ConfigError> {
+    read_yaml(path.as_ref()).map(T::from_yaml)
+}
+
+pub(crate) fn read_to_string(path: &Path) -> Result<String, ConfigError> {
+    let bytes = fs::read(path).map_err(|e| ConfigError::io(path, e))?;
+
+    decode_text(&bytes)
+        .map(Cow::into_owned)
+        .map_err(|e| ConfigError::io(path, e))
+}
+
+// Windows tools like to start files with a byte order mark, which the parsers reject
+fn decode_text(bytes: &[u8]) -> io::Result<Cow<'_, str>> {
+    let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidData, message);
+    let utf8 = |bytes| {
+        std::str::from_utf8(bytes)
+            .map(Cow::Borrowed)
+            .map_err(|e| invalid(format!("stream did not contain valid UTF-8: {}", e)))
+    };
+    let utf16 = |bytes: &[u8], from_bytes: fn([u8; 2]) -> u16| {
+        if !bytes.len().is_multiple_of(2) {
+            return Err(invalid(
+                "UTF-16 data has an odd number of bytes".to_string(),
+            ));
+        }
+
+        let units: Vec<u16> = bytes
+            .chunks_exact(2)
+            .map(|pair| from_bytes([pair[0], pair[1]]))
+            .collect();
+
+        String::from_utf16(&units)
+            .map(Cow::Owned)
+            .map_err(|e| invalid(format!("stream did not contain valid UTF-16: {}", e)))
+    };
+
+    match bytes {
+        [0xEF, 0xBB, 0xBF, rest @ ..] => utf8(rest),
+        [0xFF, 0xFE, rest @ ..] => utf16(rest, u16::from_le_bytes),
+        [0xFE, 0xFF, rest @ ..] => utf16(rest, u16::from_be_bytes),
+        _ => utf8(bytes),
+    }
+}
+
+/// Creates a configuration struct from YAML bytes, such as a file that was read or downloaded elsewhere.
+/// A UTF-8 byte order mark is skipped, and UTF-16 with a byte order mark is converted;
+/// anything else has to be UTF-8. The file loaders read files the same way.
+/// Text that isn't valid is returned as [`ConfigError::Io`] with an `InvalidData` error, and everything has an empty path.
+/// Output type must impl YamlConfig
+pub fn load_from_yaml_bytes<T: YamlConfig>(bytes: &[u8]) -> Result<T, ConfigError> {
+    let data = decode_text(bytes).map_err(|e| ConfigError::io("", e))?;
+
+    load_from_yaml_str(&data)
+}
+
+/// Creates a configuration struct from JSON bytes, such as a file that was read or downloaded elsewhere.
+/// A UTF-8 byte order mark is skipped, and UTF-16 with a byte order mark is converted;
+/// anything else has to be UTF-8. The file loaders read files the same way.
+/// Text that isn't valid is returned as [`ConfigError::Io`] with an `InvalidData` error, and everything has an empty path.
+/// Output type must impl JsonConfig
+/// ### Example
+/// ```rust
+/// # use rsconfig::{files, JsonConfig};
+/// # struct T { test: bool }
+/// # impl JsonConfig for T {
+/// #     fn from_json(val: serde_json::Value) -> Self { T { test: val["test"].as_bool().unwrap() } }
+/// #     fn save_json(&self, path: &str) -> std::io::Result<()> { Ok(()) }
+/// # }
+/// // exported by a tool that adds a UTF-8 byte order mark
+/// let config: T = files::load_from_json_bytes(b"\xEF\xBB\xBF{\"test\": true}")?;
+/// assert!(config.test);
+/// # Ok::<(), rsconfig::ConfigError>(())
+/// ```
+pub fn load_from_json_bytes<T: JsonConfig>(bytes: &[u8]) -> Result<T, ConfigError> {
+    let data = decode_text(bytes).map_err(|e| ConfigError::io("", e))?;
+
+    load_from_json_str(&data)
+}
+
+fn read_yaml(path: &Path) -> Result<Vec<Yaml>, ConfigError> {
+    parse_yaml(&read_to_string(path)?, path)
+}
+
+/// Creates a configuration struct from a YAML string, for configs that don't come from a file.
+/// Parse errors are returned as [`ConfigError::YamlParse`] with an empty path, and include the line and column.
+/// Output type must impl YamlConfig
+/// ### Example
+/// ```rust
+/// # use rsconfig::{files, YamlConfig};
+/// # struct T;
+/// # impl YamlConfig for T {
+/// #     fn from_yaml(yaml: Vec<yaml_rust::Yaml>) -> Self { T }
+/// #     fn save_yaml(&self, path: &str) -> std::io::Result<()> { Ok(()) }
+/// # }
+/// let err = files::load_from_yaml_str::<T>("name: demo\ntest: @true").err().unwrap();
+/// assert_eq!(err.location(), Some((2, 7)));
+/// ```
+pub fn load_from_yaml_str<T: YamlConfig>(data: &str) -> Result<T, ConfigError> {
+    parse_yaml(data, Path::new("")).map(T::from_yaml)
+}
 
-    T::from_yaml(yaml)
+fn parse_yaml(data: &str, path: &Path) -> Result<Vec<Yaml>, ConfigError> {
+    YamlLoader::load_from_str(data).map_err(|source| ConfigError::YamlParse {
+        path: path.into(),
+        source,
+    })
 }
 
 /// Loads a configuration struct from a JSON file.
+/// Panics if the file can't be read or parsed, use [`try_load_from_json`] to handle the error instead.
 /// Output type must impl JsonConfig
-pub fn load_from_json<T: JsonConfig>(path: &str) -> T {
-    let data = fs::read_to_string(path).expect("Failed to read file");
-    let val: Value = serde_json::from_str(&data).unwrap();
+pub fn load_from_json<T: JsonConfig>(path: impl AsRef<Path>) -> T {
+    try_load_from_json(path).unwrap_or_else(|e| panic!("{}", e))
+}
+
+/// Loads a configuration struct from a JSON file, returning an error if it can't be read or parsed.
+/// Output type must impl JsonConfig
+pub fn try_load_from_json<T: JsonConfig>(path: impl AsRef<Path>) -> Result<T, ConfigError> {
+    read_json(path.as_ref()).map(T::from_json)
+}
+
+fn read_json(path: &Path) -> Result<Value, ConfigError> {
+    parse_json(&read_to_string(path)?, path)
+}
+
+/// Creates a configuration struct from a JSON string, for configs that don't come from a file.
+/// Parse errors are returned as [`ConfigError::JsonParse`] with an empty path, and include the line and column.
+/// Output type must impl JsonConfig
+pub fn load_from_json_str<T: JsonConfig>(data: &str) -> Result<T, ConfigError> {
+    parse_json(data, Path::new("")).map(T::from_json)
+}
+
+fn parse_json(data: &str, path: &Path) -> Result<Value, ConfigError> {
+    serde_json::from_str(data).map_err(|source| ConfigError::JsonParse {
+        path: path.into(),
+        source,
+    })
+}
+
+/// Loads a configuration struct from YAML read from a reader, such as a socket or an in-memory buffer.
+/// The reader is read to EOF and decoded like [`load_from_yaml_bytes`]. Errors have an empty path:
+/// read errors and invalid text are returned as [`ConfigError::Io`], and invalid YAML as [`ConfigError::YamlParse`].
+/// Output type must impl YamlConfig
+/// ### Example
+/// ```rust,no_run
+/// # use rsconfig::{files, YamlConfig};
+/// # fn example<T: YamlConfig>() -> Result<(), Box<dyn std::error::Error>> {
+/// let stream = std::net::TcpStream::connect("127.0.0.1:7700")?;
+/// let config: T = files::load_from_yaml_reader(stream)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn load_from_yaml_reader<T: YamlConfig, R: Read>(mut reader: R) -> Result<T, ConfigError> {
+    // yaml-rust can only parse a whole string
+    let mut data = Vec::new();
+    reader
+        .read_to_end(&mut data)
+        .map_err(|e| ConfigError::io("", e))?;
+
+    load_from_yaml_bytes(&data)
+}
+
+/// Loads a configuration struct from JSON read from a reader, such as a socket or an in-memory buffer.
+/// The JSON is parsed as it's read rather than being buffered into a string first, and trailing data other than whitespace is an error.
+/// Errors have an empty path: read errors are returned as [`ConfigError::Io`], and invalid or truncated JSON as [`ConfigError::JsonParse`].
+/// Output type must impl JsonConfig
+/// ### Example
+/// ```rust
+/// # use rsconfig::{files, JsonConfig};
+/// # struct T { test: bool }
+/// # impl JsonConfig for T {
+/// #     fn from_json(val: serde_json::Value) -> Self { T { test: val["test"].as_bool().unwrap() } }
+/// #     fn save_json(&self, path: &str) -> std::io::Result<()> { Ok(()) }
+/// # }
+/// let decrypted: Vec<u8> = br#"{"test": true}"#.to_vec();
+/// let config: T = files::load_from_json_reader(decrypted.as_slice())?;
+/// assert!(config.test);
+/// # Ok::<(), rsconfig::ConfigError>(())
+/// ```
+pub fn load_from_json_reader<T: JsonConfig, R: Read>(reader: R) -> Result<T, ConfigError> {
+    // serde_json reads a byte at a time, so unbuffered readers like sockets need a buffer
+    serde_json::from_reader(io::BufReader::new(reader))
+        .map(T::from_json)
+        .map_err(|source| {
+            if source.is_io() {
+                ConfigError::io("", source.into())
+            } else {
+                ConfigError::JsonParse {
+                    path: PathBuf::new(),
+                    source,
+                }
+            }
+        })
+}
+
+/// Loads a configuration struct from a JSON5 file, which allows comments, trailing commas, single quotes and unquoted keys.
+/// The parsed document is handed to `JsonConfig::from_json`, so no extra trait is needed.
+/// `Infinity` and `NaN` can't be represented in JSON and become null.
+/// Panics if the file can't be read or parsed, use [`try_load_from_json5`] to handle the error instead.
+/// Output type must impl JsonConfig
+#[cfg(feature = "json5")]
+pub fn load_from_json5<T: JsonConfig>(path: impl AsRef<Path>) -> T {
+    try_load_from_json5(path).unwrap_or_else(|e| panic!("{}", e))
+}
+
+/// Loads a configuration struct from a JSON5 file, returning an error if it can't be read or parsed.
+/// Output type must impl JsonConfig
+#[cfg(feature = "json5")]
+pub fn try_load_from_json5<T: JsonConfig>(path: impl AsRef<Path>) -> Result<T, ConfigError> {
+    read_json5(path.as_ref()).map(T::from_json)
+}
+
+#[cfg(feature = "json5")]
+fn read_json5(path: &Path) -> Result<Value, ConfigError> {
+    parse_json5(&read_to_string(path)?, path)
+}
+
+#[cfg(feature = "json5")]
+fn parse_json5(data: &str, path: &Path) -> Result<Value, ConfigError> {
+    json5::from_str(data).map_err(|e| ConfigError::parse(path, "JSON5", e))
+}
+
+/// Loads a configuration struct from a TOML file.
+/// Panics if the file can't be read or parsed, use [`try_load_from_toml`] to handle the error instead.
+/// Output type must impl TomlConfig
+pub fn load_from_toml<T: TomlConfig>(path: impl AsRef<Path>) -> T {
+    try_load_from_toml(path).unwrap_or_else(|e| panic!("{}", e))
+}
+
+/// Loads a configuration struct from a TOML file, returning an error if it can't be read or parsed.
+/// Output type must impl TomlConfig
+pub fn try_load_from_toml<T: TomlConfig>(path: impl AsRef<Path>) -> Result<T, ConfigError> {
+    read_toml(path.as_ref()).map(T::from_toml)
+}
+
+fn read_toml(path: &Path) -> Result<toml::Value, ConfigError> {
+    parse_toml(&read_to_string(path)?, path)
+}
+
+fn parse_toml(data: &str, path: &Path) -> Result<toml::Value, ConfigError> {
+    toml::from_str(data).map_err(|e| ConfigError::parse(path, "TOML", e))
+}
+
+/// Loads a configuration struct from an INI file.
+/// Panics if the file can't be read or parsed, use [`try_load_from_ini`] to handle the error instead.
+/// Output type must impl IniConfig
+pub fn load_from_ini<T: IniConfig>(path: impl AsRef<Path>) -> T {
+    try_load_from_ini(path).unwrap_or_else(|e| panic!("{}", e))
+}
+
+/// Loads a configuration struct from an INI file, returning an error if it can't be read or parsed.
+/// Output type must impl IniConfig
+pub fn try_load_from_ini<T: IniConfig>(path: impl AsRef<Path>) -> Result<T, ConfigError> {
+    read_ini(path.as_ref()).map(T::from_ini)
+}
+
+fn read_ini(path: &Path) -> Result<ini::Ini, ConfigError> {
+    parse_ini(&read_to_string(path)?, path)
+}
+
+fn parse_ini(data: &str, path: &Path) -> Result<ini::Ini, ConfigError> {
+    data.parse::<ini::Ini>()
+        .map_err(|e| ConfigError::parse(path, "INI", e))
+}
+
+/// Loads a configuration struct from a Java-style `.properties` file.
+/// Files are read as UTF-8, falling back to ISO-8859-1 (the encoding `java.util.Properties` uses) if they aren't valid UTF-8.
+/// Panics if the file can't be read or parsed, use [`try_load_from_properties`] to handle the error instead.
+/// Output type must impl PropertiesConfig
+pub fn load_from_properties<T: PropertiesConfig>(path: impl AsRef<Path>) -> T {
+    try_load_from_properties(path).unwrap_or_else(|e| panic!("{}", e))
+}
+
+/// Loads a configuration struct from a `.properties` file, returning an error if it can't be read or parsed.
+/// Output type must impl PropertiesConfig
+pub fn try_load_from_properties<T: PropertiesConfig>(
+    path: impl AsRef<Path>,
+) -> Result<T, ConfigError> {
+    read_properties(path.as_ref()).map(T::from_properties)
+}
+
+fn read_properties(path: &Path) -> Result<properties::Properties, ConfigError> {
+    parse_properties(&read_properties_text(path)?, path)
+}
+
+fn read_properties_text(path: &Path) -> Result<String, ConfigError> {
+    let bytes = fs::read(path).map_err(|e| ConfigError::io(path, e))?;
+
+    match String::from_utf8(bytes) {
+        Ok(data) => Ok(data),
+        // every byte is its own code point in ISO-8859-1
+        Err(e) => Ok(e.into_bytes().into_iter().map(char::from).collect()),
+    }
+}
+
+fn parse_properties(data: &str, path: &Path) -> Result<properties::Properties, ConfigError> {
+    data.parse::<properties::Properties>()
+        .map_err(|e| ConfigError::parse(path, "properties", e))
+}
+
+/// Loads a configuration struct from an XML file.
+/// Panics if the file can't be read or parsed, use [`try_load_from_xml`] to handle the error instead.
+/// Output type must impl XmlConfig
+#[cfg(feature = "xml")]
+pub fn load_from_xml<T: XmlConfig>(path: impl AsRef<Path>) -> T {
+    try_load_from_xml(path).unwrap_or_else(|e| panic!("{}", e))
+}
+
+/// Loads a configuration struct from an XML file, returning an error if it can't be read or parsed.
+/// Malformed XML is returned as [`ConfigError::Parse`].
+/// Output type must impl XmlConfig
+#[cfg(feature = "xml")]
+pub fn try_load_from_xml<T: XmlConfig>(path: impl AsRef<Path>) -> Result<T, ConfigError> {
+    read_xml(path.as_ref()).map(T::from_xml)
+}
+
+#[cfg(feature = "xml")]
+fn read_xml(path: &Path) -> Result<xmltree::Element, ConfigError> {
+    let file = File::open(path).map_err(|e| ConfigError::io(path, e))?;
+
+    xmltree::Element::parse(file).map_err(|e| ConfigError::parse(path, "XML", e))
+}
+
+#[cfg(feature = "xml")]
+fn parse_xml(data: &str, path: &Path) -> Result<xmltree::Element, ConfigError> {
+    xmltree::Element::parse(data.as_bytes()).map_err(|e| ConfigError::parse(path, "XML", e))
+}
+
+/// Loads a configuration struct from a MessagePack file.
+/// Panics if the file can't be read or decoded, use [`try_load_from_msgpack`] to handle the error instead.
+/// Output type must impl MsgpackConfig
+#[cfg(feature = "rmp")]
+pub fn load_from_msgpack<T: MsgpackConfig>(path: impl AsRef<Path>) -> T {
+    try_load_from_msgpack(path).unwrap_or_else(|e| panic!("{}", e))
+}
+
+/// Loads a configuration struct from a MessagePack file, returning an error if it can't be read or decoded.
+/// Malformed or truncated data is returned as [`ConfigError::Parse`].
+/// Output type must impl MsgpackConfig
+#[cfg(feature = "rmp")]
+pub fn try_load_from_msgpack<T: MsgpackConfig>(path: impl AsRef<Path>) -> Result<T, ConfigError> {
+    read_msgpack(path.as_ref()).map(T::from_msgpack)
+}
+
+/// Creates a configuration struct from MessagePack bytes, for data that doesn't come from a file.
+/// The bytes must contain exactly one value, trailing bytes are an error.
+/// Errors are returned as [`ConfigError::Parse`] with an empty path.
+/// Output type must impl MsgpackConfig
+/// ### Example
+/// ```rust,no_run
+/// # use rsconfig::{files, MsgpackConfig};
+/// # fn example<T: MsgpackConfig>(payload: &[u8]) -> Result<(), rsconfig::ConfigError> {
+/// // a message received over the network
+/// let config: T = files::from_msgpack_bytes(payload)?;
+/// # Ok(())
+/// # }
+/// ```
+#[cfg(feature = "rmp")]
+pub fn from_msgpack_bytes<T: MsgpackConfig>(bytes: &[u8]) -> Result<T, ConfigError> {
+    decode_msgpack(bytes, Path::new("")).map(T::from_msgpack)
+}
+
+#[cfg(feature = "rmp")]
+fn read_msgpack(path: &Path) -> Result<rmpv::Value, ConfigError> {
+    let bytes = fs::read(path).map_err(|e| ConfigError::io(path, e))?;
+
+    decode_msgpack(&bytes, path)
+}
+
+#[cfg(feature = "rmp")]
+fn decode_msgpack(mut bytes: &[u8], path: &Path) -> Result<rmpv::Value, ConfigError> {
+    let val = rmpv::decode::read_value(&mut bytes)
+        .map_err(|e| ConfigError::parse(path, "MessagePack", e))?;
+
+    if !bytes.is_empty() {
+        return Err(ConfigError::parse(
+            path,
+            "MessagePack",
+            format!("{} unexpected trailing bytes", bytes.len()),
+        ));
+    }
+
+    Ok(val)
+}
+
+/// Loads a configuration struct from a CBOR file. Indefinite-length items are supported.
+/// Panics if the file can't be read or decoded, use [`try_load_from_cbor`] to handle the error instead.
+/// Output type must impl CborConfig
+#[cfg(feature = "cbor")]
+pub fn load_from_cbor<T: CborConfig>(path: impl AsRef<Path>) -> T {
+    try_load_from_cbor(path).unwrap_or_else(|e| panic!("{}", e))
+}
+
+/// Loads a configuration struct from a CBOR file, returning an error if it can't be read or decoded.
+/// Malformed, truncated or trailing data is returned as [`ConfigError::Parse`].
+/// Output type must impl CborConfig
+#[cfg(feature = "cbor")]
+pub fn try_load_from_cbor<T: CborConfig>(path: impl AsRef<Path>) -> Result<T, ConfigError> {
+    read_cbor(path.as_ref()).map(T::from_cbor)
+}
+
+#[cfg(feature = "cbor")]
+fn read_cbor(path: &Path) -> Result<ciborium::Value, ConfigError> {
+    let data = fs::read(path).map_err(|e| ConfigError::io(path, e))?;
+    let mut bytes = data.as_slice();
+
+    let val = ciborium::from_reader(&mut bytes).map_err(|e| ConfigError::parse(path, "CBOR", e))?;
+
+    if !bytes.is_empty() {
+        return Err(ConfigError::parse(
+            path,
+            "CBOR",
+            format!("{} unexpected trailing bytes", bytes.len()),
+        ));
+    }
+
+    Ok(val)
+}
+
+/// Loads a configuration struct from a bincode-encoded binary file.
+/// Corrupt or truncated files are returned as [`ConfigError::Parse`] instead of panicking.
+/// Output type must impl BinaryConfig
+#[cfg(feature = "bincode")]
+pub fn load_from_binary<T: BinaryConfig>(path: impl AsRef<Path>) -> Result<T, ConfigError> {
+    let path = path.as_ref();
+    let bytes = fs::read(path).map_err(|e| ConfigError::io(path, e))?;
+
+    T::from_bytes(&bytes).map_err(|e| ConfigError::parse(path, "bincode", e))
+}
+
+/// Loads a configuration struct from a dotenv (.env) file.
+/// Supports `KEY=value` lines, `export KEY=value`, `#` comments, and single or double quoted values.
+/// Single quoted values are taken literally; double quoted values can span lines and understand `\n`, `\t`, `\"` and `\\` escapes.
+/// Panics if the file can't be read or parsed, use [`try_load_from_dotenv`] to handle the error instead.
+/// Output type must impl EnvFileConfig
+pub fn load_from_dotenv<T: EnvFileConfig>(path: impl AsRef<Path>) -> T {
+    try_load_from_dotenv(path).unwrap_or_else(|e| panic!("{}", e))
+}
+
+/// Loads a configuration struct from a dotenv (.env) file, returning an error if it can't be read or parsed.
+/// Output type must impl EnvFileConfig
+pub fn try_load_from_dotenv<T: EnvFileConfig>(path: impl AsRef<Path>) -> Result<T, ConfigError> {
+    read_dotenv(path.as_ref()).map(T::from_env_map)
+}
+
+fn read_dotenv(path: &Path) -> Result<HashMap<String, String>, ConfigError> {
+    parse_dotenv(&read_to_string(path)?).map_err(|e| ConfigError::parse(path, "dotenv", e))
+}
+
+fn parse_dotenv(data: &str) -> Result<HashMap<String, String>, String> {
+    let mut vars = HashMap::new();
+    let mut lines = data.lines().enumerate();
+
+    while let Some((i, line)) = lines.next() {
+        let line = line.trim();
+
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let line = line.strip_prefix("export ").unwrap_or(line).trim_start();
+        let (key, value) = line
+            .split_once('=')
+            .ok_or_else(|| format!("line {}: expected `KEY=value`", i + 1))?;
+
+        let key = key.trim();
+        if key.is_empty() || key.contains(char::is_whitespace) {
+            return Err(format!("line {}: invalid key `{}`", i + 1, key));
+        }
+
+        let value = value.trim_start();
+        let value = match value.chars().next() {
+            Some('\'') => {
+                let end = value[1..]
+                    .find('\'')
+                    .ok_or_else(|| format!("line {}: unterminated single quote", i + 1))?;
+
+                value[1..end + 1].to_string()
+            }
+            Some('"') => {
+                // double quoted values may continue on the following lines
+                let mut raw = value[1..].to_string();
+                let start = i;
+
+                loop {
+                    if let Some(end) = closing_quote(&raw) {
+                        raw.truncate(end);
+                        break;
+                    }
+
+                    let (_, next) = lines
+                        .next()
+                        .ok_or_else(|| format!("line {}: unterminated double quote", start + 1))?;
+                    raw.push('\n');
+                    raw.push_str(next);
+                }
+
+                unescape(&raw)
+            }
+            // unquoted values end at an inline comment
+            _ => match value.find(" #") {
+                Some(comment) => value[..comment].trim_end().to_string(),
+                None => value.trim_end().to_string(),
+            },
+        };
+
+        vars.insert(key.to_string(), value);
+    }
+
+    Ok(vars)
+}
+
+// finds the first double quote that isn't escaped
+fn closing_quote(raw: &str) -> Option<usize> {
+    let mut escaped = false;
+
+    for (i, c) in raw.char_indices() {
+        match c {
+            '\\' if !escaped => escaped = true,
+            '"' if !escaped => return Some(i),
+            _ => escaped = false,
+        }
+    }
+
+    None
+}
+
+fn unescape(raw: &str) -> String {
+    let mut value = String::with_capacity(raw.len());
+    let mut chars = raw.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            value.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            Some('n') => value.push('\n'),
+            Some('t') => value.push('\t'),
+            Some('r') => value.push('\r'),
+            Some(other) => value.push(other),
+            None => value.push('\\'),
+        }
+    }
+
+    value
+}
+
+/// Loads a configuration struct from a file, choosing the format from its extension.
+/// Returns an error naming the file if it can't be read or parsed, if its extension isn't recognized, or if the format isn't supported.
+/// Output type must impl FileConfig
+/// Formats other than YAML, JSON and JSON5 are only supported if the type opts in through the matching `FileConfig::try_from_*` method
+/// Files named `.env` are loaded as dotenv files
+/// Extensions are matched ignoring case and surrounding whitespace, so `CONFIG.YML` loads as YAML
+/// ### Example
+/// ```rust,no_run
+/// # use rsconfig::{files, ConfigError, FileConfig};
+/// # fn example<T: FileConfig>() {
+/// match files::load_from_file::<T>("config.conf") {
+///     Ok(config) => { /* ... */ }
+///     Err(ConfigError::UnsupportedExtension { extension, supported, .. }) => {
+///         eprintln!("can't load .{} files, use one of: {}", extension, supported.join(", "));
+///     }
+///     Err(e) => eprintln!("{}", e),
+/// }
+/// # }
+/// ```
+pub fn load_from_file<T: FileConfig>(path: impl AsRef<Path>) -> Result<T, ConfigError> {
+    try_load_from_file(path)
+}
+
+/// Same as [`load_from_file`], named to match [`try_load_from_yaml`] and [`try_load_from_json`].
+pub fn try_load_from_file<T: FileConfig>(path: impl AsRef<Path>) -> Result<T, ConfigError> {
+    load_from_file_with(path, LoadOptions::new())
+}
+
+/// Options for [`load_from_file_with`].
+/// ### Example
+/// ```rust,no_run
+/// # use rsconfig::{files::{self, LoadOptions}, FileConfig};
+/// # fn example<T: FileConfig>() -> Result<(), rsconfig::ConfigError> {
+/// // database_url: ${DATABASE_URL}
+/// // log_level: ${LOG_LEVEL:-info}
+/// let config: T = files::load_from_file_with("config.yml", LoadOptions::new().expand_env(true))?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LoadOptions {
+    expand_env: bool,
+}
+
+impl LoadOptions {
+    /// The options [`load_from_file`] uses.
+    pub fn new() -> Self {
+        Self { expand_env: false }
+    }
+
+    /// Whether to replace environment variable placeholders in the file's text before parsing it, false by default.
+    /// - `${VAR}` is replaced by the variable's value, and is an error naming the variable if it isn't set
+    /// - `${VAR:-default}` falls back to `default` when the variable isn't set or is empty
+    /// - `$$` is a literal `$`, and a `$` not followed by `{` is kept as it is
+    ///
+    /// Binary formats like MessagePack are loaded as they are.
+    pub fn expand_env(mut self, expand_env: bool) -> Self {
+        self.expand_env = expand_env;
+        self
+    }
+}
+
+impl Default for LoadOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Same as [`load_from_file`], with [`LoadOptions`] to change how the file is read.
+pub fn load_from_file_with<T: FileConfig>(
+    path: impl AsRef<Path>,
+    options: LoadOptions,
+) -> Result<T, ConfigError> {
+    let path = path.as_ref();
+    let extension = format_extension(path)?;
+
+    // formats beyond YAML and JSON need the type to opt in
+    let opt_in = |config: Option<T>, format| {
+        config.ok_or_else(|| ConfigError::UnsupportedFormat {
+            path: path.into(),
+            format,
+        })
+    };
+
+    // text formats go through here so placeholders are expanded before parsing
+    let text = |read: fn(&Path) -> Result<String, ConfigError>| {
+        let data = read(path)?;
+
+        if options.expand_env {
+            expand_vars(&data, path, &|name| env::var(name).ok())
+        } else {
+            Ok(data)
+        }
+    };
+
+    // `CONFIG.YML` and `config.yml ` are still YAML
+    match extension.trim().to_lowercase().as_str() {
+        "yaml" | "yml" => parse_yaml(&text(read_to_string)?, path).map(T::from_yaml),
+        "json" => parse_json(&text(read_to_string)?, path).map(T::from_json),
+        #[cfg(feature = "json5")]
+        "json5" => parse_json5(&text(read_to_string)?, path).map(T::from_json),
+        "toml" => opt_in(
+            T::try_from_toml(parse_toml(&text(read_to_string)?, path)?),
+            "TOML",
+        ),
+        "ini" => opt_in(
+            T::try_from_ini(parse_ini(&text(read_to_string)?, path)?),
+            "INI",
+        ),
+        "properties" => opt_in(
+            T::try_from_properties(parse_properties(&text(read_properties_text)?, path)?),
+            "properties",
+        ),
+        #[cfg(feature = "xml")]
+        "xml" => opt_in(
+            T::try_from_xml(parse_xml(&text(read_to_string)?, path)?),
+            "XML",
+        ),
+        #[cfg(feature = "rmp")]
+        "msgpack" | "mp" => opt_in(T::try_from_msgpack(read_msgpack(path)?), "MessagePack"),
+        #[cfg(feature = "cbor")]
+        "cbor" => opt_in(T::try_from_cbor(read_cbor(path)?), "CBOR"),
+        #[cfg(feature = "bincode")]
+        "bin" => opt_in(
+            T::try_from_binary(fs::read(path).map_err(|e| ConfigError::io(path, e))?)
+                .transpose()
+                .map_err(|e| ConfigError::parse(path, "binary", e))?,
+            "binary",
+        ),
+        "env" => opt_in(
+            T::try_from_env_map(
+                parse_dotenv(&text(read_to_string)?)
+                    .map_err(|e| ConfigError::parse(path, "dotenv", e))?,
+            ),
+            "dotenv",
+        ),
+        _ => Err(ConfigError::UnsupportedExtension {
+            path: path.into(),
+            extension: extension.into_owned(),
+            supported: supported_extensions(),
+        }),
+    }
+}
+
+// replaces `${VAR}` and `${VAR:-default}` placeholders, see `LoadOptions::expand_env`
+pub(crate) fn expand_vars(
+    data: &str,
+    path: &Path,
+    lookup: &dyn Fn(&str) -> Option<String>,
+) -> Result<String, ConfigError> {
+    let mut expanded = String::with_capacity(data.len());
+    let mut rest = data;
+
+    while let Some(i) = rest.find('$') {
+        expanded.push_str(&rest[..i]);
+        rest = &rest[i..];
+
+        if let Some(after) = rest.strip_prefix("$$") {
+            expanded.push('$');
+            rest = after;
+            continue;
+        }
+
+        let Some(after) = rest.strip_prefix("${") else {
+            expanded.push('$');
+            rest = &rest[1..];
+            continue;
+        };
+
+        let end = after.find('}').ok_or_else(|| {
+            ConfigError::parse(
+                path,
+                "placeholders",
+                "`${` is never closed, use `$$` for a literal `$`",
+            )
+        })?;
+        let (name, default) = match after[..end].split_once(":-") {
+            Some((name, default)) => (name, Some(default)),
+            None => (&after[..end], None),
+        };
+
+        let valid = name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if !valid {
+            return Err(ConfigError::parse(
+                path,
+                "placeholders",
+                format!("`${{{}}}` isn't a valid variable name", name),
+            ));
+        }
+
+        match (lookup(name), default) {
+            (Some(value), Some(default)) if value.is_empty() => expanded.push_str(default),
+            (Some(value), _) => expanded.push_str(&value),
+            (None, Some(default)) => expanded.push_str(default),
+            (None, None) => {
+                return Err(ConfigError::UnsetVariable {
+                    path: path.into(),
+                    variable: name.to_string(),
+                })
+            }
+        }
+
+        rest = &after[end + 1..];
+    }
+
+    expanded.push_str(rest);
+    Ok(expanded)
+}
+
+// reads a YAML, JSON, JSON5 or TOML file into a JSON value, for merging it with other sources
+pub(crate) fn read_value(path: &Path) -> Result<Value, ConfigError> {
+    let extension = format_extension(path)?;
+
+    let unsupported = |format| {
+        Err(ConfigError::UnsupportedFormat {
+            path: path.into(),
+            format,
+        })
+    };
+
+    match extension.trim().to_lowercase().as_str() {
+        // only the first document is used, like the YamlConfig examples
+        "yaml" | "yml" => match read_yaml(path)?.first() {
+            Some(doc) => yaml_to_json(doc).map_err(|e| ConfigError::parse(path, "YAML", e)),
+            None => Ok(Value::Null),
+        },
+        "json" => read_json(path),
+        #[cfg(feature = "json5")]
+        "json5" => read_json5(path),
+        "toml" => {
+            serde_json::to_value(read_toml(path)?).map_err(|e| ConfigError::parse(path, "TOML", e))
+        }
+        "ini" => unsupported("INI"),
+        "properties" => unsupported("properties"),
+        #[cfg(feature = "xml")]
+        "xml" => unsupported("XML"),
+        #[cfg(feature = "rmp")]
+        "msgpack" | "mp" => unsupported("MessagePack"),
+        #[cfg(feature = "cbor")]
+        "cbor" => unsupported("CBOR"),
+        #[cfg(feature = "bincode")]
+        "bin" => unsupported("binary"),
+        "env" => unsupported("dotenv"),
+        _ => Err(ConfigError::UnsupportedExtension {
+            path: path.into(),
+            extension: extension.into_owned(),
+            supported: supported_extensions(),
+        }),
+    }
+}
+
+/// Loads a configuration struct from a file like [`load_from_file`], or returns `T::default()` if the file doesn't exist.
+/// Only a missing file falls back to the default: a file that's empty or can't be parsed is still an error,
+/// so a corrupted config isn't silently replaced.
+/// Output type must impl FileConfig and Default
+/// ### Example
+/// ```rust,no_run
+/// # use rsconfig::{files, FileConfig};
+/// # fn example<T: FileConfig + Default>() -> Result<(), rsconfig::ConfigError> {
+/// // first run, nothing has been saved yet
+/// let config: T = files::load_or_default("config.yml")?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn load_or_default<T: FileConfig + Default>(path: impl AsRef<Path>) -> Result<T, ConfigError> {
+    or_default(path.as_ref(), "config", |path| try_load_from_file(path))
+}
+
+/// Same as [`load_or_default`], for YAML files.
+/// Output type must impl YamlConfig and Default
+pub fn load_yaml_or_default<T: YamlConfig + Default>(
+    path: impl AsRef<Path>,
+) -> Result<T, ConfigError> {
+    or_default(path.as_ref(), "YAML", |path| try_load_from_yaml(path))
+}
+
+/// Same as [`load_or_default`], for JSON files.
+/// Output type must impl JsonConfig and Default
+pub fn load_json_or_default<T: JsonConfig + Default>(
+    path: impl AsRef<Path>,
+) -> Result<T, ConfigError> {
+    or_default(path.as_ref(), "JSON", |path| try_load_from_json(path))
+}
+
+/// Loads a partial config overlay (see [`PartialConfig`]) from a YAML file.
+/// Keys missing from the file are left for the overlay's `from_yaml` to treat as unset, and an empty file
+/// (or one holding only comments) is passed on as an empty map, so it overrides nothing.
+/// Output type must impl PartialConfig and YamlConfig
+pub fn load_partial_from_yaml<P: PartialConfig + YamlConfig>(
+    path: impl AsRef<Path>,
+) -> Result<P, ConfigError> {
+    let mut docs = read_yaml(path.as_ref())?;
+
+    if docs.first().is_none_or(Yaml::is_null) {
+        docs = vec![Yaml::Hash(Default::default())];
+    }
+
+    Ok(P::from_yaml(docs))
+}
+
+/// Same as [`load_partial_from_yaml`], for JSON files. An empty file is passed on as an empty object.
+/// Output type must impl PartialConfig and JsonConfig
+pub fn load_partial_from_json<P: PartialConfig + JsonConfig>(
+    path: impl AsRef<Path>,
+) -> Result<P, ConfigError> {
+    let path = path.as_ref();
+    let data = read_to_string(path)?;
+
+    let value = match data.trim() {
+        "" => Value::Null,
+        _ => parse_json(&data, path)?,
+    };
+
+    match value {
+        Value::Null => Ok(P::from_json(Value::Object(Default::default()))),
+        value => Ok(P::from_json(value)),
+    }
+}
+
+/// Same as [`load_partial_from_yaml`], choosing the format from the extension like [`load_from_file`].
+/// Empty YAML and JSON files override nothing, other formats are loaded as they are.
+/// Output type must impl PartialConfig and FileConfig
+pub fn load_partial_from_file<P: PartialConfig + FileConfig>(
+    path: impl AsRef<Path>,
+) -> Result<P, ConfigError> {
+    let path = path.as_ref();
+
+    match format_extension(path)?.trim().to_lowercase().as_str() {
+        "yaml" | "yml" => load_partial_from_yaml(path),
+        "json" => load_partial_from_json(path),
+        _ => try_load_from_file(path),
+    }
+}
+
+/// Loads a partial config overlay from each of several files like [`load_partial_from_file`],
+/// and applies them onto `target` in order, so later files override earlier ones.
+/// Missing files are skipped, and any other error is returned before `target` is changed.
+/// ### Example
+/// ```rust,no_run
+/// # use rsconfig::{files, FileConfig, PartialConfig};
+/// # fn example<P: PartialConfig + FileConfig>(mut config: P::Complete) -> Result<(), rsconfig::ConfigError> {
+/// // the user's file only overrides log_level
+/// files::apply_partial_files::<P>(&mut config, &["/etc/myapp.yml", "/home/me/.myapp.yml"])?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn apply_partial_files<P: PartialConfig + FileConfig>(
+    target: &mut P::Complete,
+    paths: &[impl AsRef<Path>],
+) -> Result<(), ConfigError> {
+    let mut partials = Vec::new();
+
+    for path in paths {
+        match load_partial_from_file::<P>(path.as_ref()) {
+            Ok(partial) => partials.push(partial),
+            Err(ConfigError::Io { source, .. }) if source.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e),
+        }
+    }
+
+    for partial in partials {
+        partial.apply_to(target);
+    }
+
+    Ok(())
+}
+
+/// Loads a configuration struct from a file like [`load_from_file`], or writes `T::default()` to it if it doesn't exist,
+/// so users have a file to edit after the first run. The default is saved like [`save_to_file`], choosing the format
+/// from the extension and creating missing parent directories.
+///
+/// The default is written to a temporary file and moved into place, so the file never appears half-written.
+/// A file that exists is never overwritten, even if it can't be parsed; the error is returned instead.
+/// If another process creates the file first, that file is loaded. On filesystems without hard links, like FAT,
+/// the file is renamed into place after checking it's still missing, which leaves a short window for that race.
+/// Output type must impl FileConfig and Default
+/// ### Example
+/// ```rust,no_run
+/// # use rsconfig::{files, FileConfig};
+/// # fn example<T: FileConfig + Default>() -> Result<(), rsconfig::ConfigError> {
+/// let config: T = files::load_or_create("config.yml")?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn load_or_create<T: FileConfig + Default>(path: impl AsRef<Path>) -> Result<T, ConfigError> {
+    let path = path.as_ref();
+
+    match try_load_from_file(path) {
+        Err(ConfigError::Io { source, .. }) if source.kind() == io::ErrorKind::NotFound => {}
+        result => return result,
+    }
+
+    let config = T::default();
+    let save = save_method::<T>(path)?;
+    create_parent_dirs(path)?;
+
+    let temp = temp_path(path);
+    let written = write_synced(
+        &temp,
+        Contents::Render(Box::new(|temp| save(&config, temp))),
+    );
+    if let Err(e) = written {
+        let _ = fs::remove_file(&temp);
+        return Err(ConfigError::write(path, e));
+    }
+
+    // linking fails if the file exists, unlike renaming, so a file created in the meantime is kept
+    let linked = match fs::hard_link(&temp, path) {
+        // filesystems without hard links, like FAT, can only rename, which replaces the target if it exists
+        Err(e)
+            if matches!(
+                e.kind(),
+                io::ErrorKind::Unsupported | io::ErrorKind::PermissionDenied
+            ) =>
+        {
+            match fs::symlink_metadata(path) {
+                Ok(_) => Err(io::ErrorKind::AlreadyExists.into()),
+                Err(e) if e.kind() == io::ErrorKind::NotFound => fs::rename(&temp, path),
+                Err(e) => Err(e),
+            }
+        }
+        result => result,
+    };
+
+    // already gone if it was renamed
+    let _ = fs::remove_file(&temp);
+
+    match linked {
+        Ok(()) => {
+            sync_parent(path);
+            Ok(config)
+        }
+        Err(e) if e.kind() == io::ErrorKind::AlreadyExists => try_load_from_file(path),
+        Err(e) => Err(ConfigError::write(path, e)),
+    }
+}
 
-    T::from_json(val)
+/// Loads a configuration struct from the first of several files that exists, like [`load_from_file`],
+/// returning the config with the path it was loaded from.
+/// Missing files are skipped, but any other error (like a file that can't be parsed) is returned right away
+/// rather than falling through to the next path.
+/// Returns [`ConfigError::NoneFound`] listing every path if none of them exist.
+/// Output type must impl FileConfig
+/// ### Example
+/// ```rust,no_run
+/// # use rsconfig::{files, FileConfig};
+/// # fn example<T: FileConfig>() -> Result<(), rsconfig::ConfigError> {
+/// let (config, path) = files::load_from_first::<T>(&[
+///     "myapp.yml",
+///     "/home/me/.config/myapp/config.yml",
+///     "/etc/myapp/config.yml",
+/// ])?;
+/// println!("loaded {}", path.display());
+/// # Ok(())
+/// # }
+/// ```
+pub fn load_from_first<T: FileConfig>(
+    paths: &[impl AsRef<Path>],
+) -> Result<(T, PathBuf), ConfigError> {
+    for path in paths {
+        let path = path.as_ref();
+
+        match try_load_from_file(path) {
+            Ok(config) => return Ok((config, path.into())),
+            Err(ConfigError::Io { source, .. }) if source.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e),
+        }
+    }
+
+    Err(ConfigError::NoneFound {
+        paths: paths.iter().map(|path| path.as_ref().into()).collect(),
+    })
+}
+
+/// Loads a configuration struct from each of several files like [`load_from_file`],
+/// and merges them in order with `MergeConfig::merge`, so later files override earlier ones.
+/// Missing files are skipped, but any other error (like a file that can't be parsed) is returned right away.
+/// Returns [`ConfigError::NoneFound`] listing every path if none of them exist.
+/// Output type must impl FileConfig and MergeConfig
+/// ### Example
+/// ```rust,no_run
+/// # use rsconfig::{files, FileConfig, MergeConfig};
+/// # fn example<T: FileConfig + MergeConfig>() -> Result<(), rsconfig::ConfigError> {
+/// // settings in the user's file win over the system-wide ones
+/// let config: T = files::load_merged(&["/etc/myapp.yml", "/home/me/.myapp.yml"])?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn load_merged<T: FileConfig + MergeConfig>(
+    paths: &[impl AsRef<Path>],
+) -> Result<T, ConfigError> {
+    let mut merged: Option<T> = None;
+
+    for path in paths {
+        let config = match try_load_from_file(path.as_ref()) {
+            Ok(config) => config,
+            Err(ConfigError::Io { source, .. }) if source.kind() == io::ErrorKind::NotFound => {
+                continue
+            }
+            Err(e) => return Err(e),
+        };
+
+        match &mut merged {
+            Some(merged) => merged.merge(config),
+            None => merged = Some(config),
+        }
+    }
+
+    merged.ok_or_else(|| ConfigError::NoneFound {
+        paths: paths.iter().map(|path| path.as_ref().into()).collect(),
+    })
+}
+
+/// The path of a config file in the current user's config directory, in a folder named after the app:
+/// - Linux and other unix systems: `$XDG_CONFIG_HOME/<app>/<file>`, or `~/.config/<app>/<file>` if it isn't set
+/// - macOS: `~/Library/Application Support/<app>/<file>`
+/// - Windows: `%APPDATA%\<app>\<file>`
+///
+/// Returns None if the directory can't be found, like when `HOME` isn't set.
+/// ### Example
+/// ```rust,no_run
+/// # use rsconfig::{files, FileConfig};
+/// # fn example<T: FileConfig>() -> Result<(), rsconfig::ConfigError> {
+/// let path = files::config_path("myapp", "config.yml").expect("no home directory");
+/// let config: T = files::load_from_file(path)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn config_path(app_name: &str, file_name: &str) -> Option<PathBuf> {
+    user_config_dir(&|name| env::var_os(name)).map(|dir| dir.join(app_name).join(file_name))
+}
+
+/// The path of a config file in the system-wide config directory, shared by every user:
+/// - Linux and other unix systems: `/etc/<app>/<file>`
+/// - macOS: `/Library/Application Support/<app>/<file>`
+/// - Windows: `%ProgramData%\<app>\<file>`
+///
+/// Returns None if the directory can't be found.
+pub fn system_config_path(app_name: &str, file_name: &str) -> Option<PathBuf> {
+    system_config_dir(&|name| env::var_os(name)).map(|dir| dir.join(app_name).join(file_name))
+}
+
+/// Loads a configuration struct from the user's config file ([`config_path`]),
+/// falling back to the system-wide one ([`system_config_path`]) if it doesn't exist, using [`load_from_first`].
+/// Output type must impl FileConfig
+/// ### Example
+/// ```rust,no_run
+/// # use rsconfig::{files, FileConfig};
+/// # fn example<T: FileConfig>() -> Result<(), rsconfig::ConfigError> {
+/// let config: T = files::load_from_standard_location("myapp", "config.yml")?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn load_from_standard_location<T: FileConfig>(
+    app_name: &str,
+    file_name: &str,
+) -> Result<T, ConfigError> {
+    let paths: Vec<PathBuf> = [
+        config_path(app_name, file_name),
+        system_config_path(app_name, file_name),
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+
+    load_from_first(&paths).map(|(config, _)| config)
+}
+
+/// Loads a configuration struct from the file given with `flag` in commandline arguments, like [`load_from_file`].
+/// See [`path_from_args`] for how the arguments are read.
+/// Output type must impl FileConfig
+/// ### Example
+/// ```rust,no_run
+/// # use rsconfig::{files, FileConfig};
+/// # fn example<T: FileConfig>() -> Result<(), rsconfig::ConfigError> {
+/// // myapp --config /etc/myapp/config.yml
+/// let args: Vec<String> = std::env::args().collect();
+/// let config: T = files::load_from_args(&args, "--config", "config.yml")?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn load_from_args<T: FileConfig>(
+    args: &[String],
+    flag: &str,
+    default_path: &str,
+) -> Result<T, ConfigError> {
+    try_load_from_file(path_from_args(args, flag, default_path)?)
+}
+
+/// Finds the config path given with `flag` (like `--config`) in commandline arguments,
+/// as either `--config <path>` or `--config=<path>`, and returns `default_path` if the flag isn't there.
+/// If the flag is given more than once the last one wins, and arguments after `--` aren't read.
+/// Returns [`ConfigError::MissingArgValue`] if the flag has no value.
+/// The path can also be given to [`save_to_file_with`] or [`save_with_backup`] to save the config back where it came from.
+/// ### Example
+/// ```rust
+/// use rsconfig::files;
+///
+/// let args = ["myapp", "--config", "/etc/myapp.yml"].map(String::from);
+/// assert_eq!(files::path_from_args(&args, "--config", "config.yml").unwrap(), std::path::Path::new("/etc/myapp.yml"));
+///
+/// let args = ["myapp", "--verbose"].map(String::from);
+/// assert_eq!(files::path_from_args(&args, "--config", "config.yml").unwrap(), std::path::Path::new("config.yml"));
+/// ```
+pub fn path_from_args(
+    args: &[String],
+    flag: &str,
+    default_path: &str,
+) -> Result<PathBuf, ConfigError> {
+    let missing = || ConfigError::MissingArgValue {
+        flag: flag.to_string(),
+    };
+
+    let mut found = None;
+    let mut args = args.iter().take_while(|arg| *arg != "--");
+
+    while let Some(arg) = args.next() {
+        let value = match arg.strip_prefix(flag) {
+            // the next argument is the value, unless it's another flag
+            Some("") => match args.next() {
+                Some(value) if !value.starts_with("--") => value,
+                _ => return Err(missing()),
+            },
+            Some(rest) => match rest.strip_prefix('=') {
+                Some("") => return Err(missing()),
+                Some(value) => value,
+                None => continue,
+            },
+            None => continue,
+        };
+
+        found = Some(value);
+    }
+
+    Ok(PathBuf::from(found.unwrap_or(default_path)))
+}
+
+// empty or relative values are ignored, like the XDG spec says
+fn env_dir(var: &dyn Fn(&str) -> Option<OsString>, name: &str) -> Option<PathBuf> {
+    var(name).map(PathBuf::from).filter(|dir| dir.is_absolute())
+}
+
+// the environment is passed in so the lookups can be tested
+pub(crate) fn user_config_dir(var: &dyn Fn(&str) -> Option<OsString>) -> Option<PathBuf> {
+    if cfg!(windows) {
+        env_dir(var, "APPDATA")
+    } else if cfg!(target_os = "macos") {
+        env_dir(var, "HOME").map(|home| home.join("Library").join("Application Support"))
+    } else {
+        env_dir(var, "XDG_CONFIG_HOME")
+            .or_else(|| env_dir(var, "HOME").map(|home| home.join(".config")))
+    }
+}
+
+pub(crate) fn system_config_dir(var: &dyn Fn(&str) -> Option<OsString>) -> Option<PathBuf> {
+    if cfg!(windows) {
+        env_dir(var, "ProgramData")
+    } else if cfg!(target_os = "macos") {
+        Some(PathBuf::from("/Library/Application Support"))
+    } else {
+        Some(PathBuf::from("/etc"))
+    }
+}
+
+fn or_default<T: Default>(
+    path: &Path,
+    format: &'static str,
+    load: impl FnOnce(&Path) -> Result<T, ConfigError>,
+) -> Result<T, ConfigError> {
+    match fs::read(path) {
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(T::default()),
+        Err(e) => return Err(ConfigError::io(path, e)),
+        // an empty YAML file would otherwise reach from_yaml with no documents
+        Ok(bytes) if bytes.iter().all(u8::is_ascii_whitespace) => {
+            return Err(ConfigError::parse(path, format, "the file is empty"))
+        }
+        Ok(_) => {}
+    }
+
+    match load(path) {
+        // removed since it was checked
+        Err(ConfigError::Io { source, .. }) if source.kind() == io::ErrorKind::NotFound => {
+            Ok(T::default())
+        }
+        result => result,
+    }
+}
+
+/// Same as [`load_from_file`], but files without a recognized extension are loaded by looking at their contents,
+/// as described in [`detect_format`].
+/// Returns [`ConfigError::UnknownFormat`] listing why each format didn't match if the contents aren't recognized either.
+/// Output type must impl FileConfig
+/// ### Example
+/// ```rust,no_run
+/// # use rsconfig::{files, FileConfig};
+/// # fn example<T: FileConfig>() -> Result<(), rsconfig::ConfigError> {
+/// // the deployment system doesn't add an extension
+/// let config: T = files::load_with_detection("/etc/myapp/appconfig")?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn load_with_detection<T: FileConfig>(path: impl AsRef<Path>) -> Result<T, ConfigError> {
+    let path = path.as_ref();
+
+    match try_load_from_file(path) {
+        Err(ConfigError::NoExtension { .. } | ConfigError::UnsupportedExtension { .. }) => {}
+        result => return result,
+    }
+
+    match sniff(&read_to_string(path)?) {
+        Ok(Sniffed::Json(val)) => Ok(T::from_json(val)),
+        Ok(Sniffed::Yaml(yaml)) => Ok(T::from_yaml(yaml)),
+        Err(failures) => Err(ConfigError::UnknownFormat {
+            path: path.into(),
+            failures,
+        }),
+    }
+}
+
+/// Guesses the format of config data from its contents, for data without a file name to go by.
+/// Data starting with `{` or `[` (after whitespace) is tried as JSON first, then anything is tried as YAML.
+/// YAML only counts if it holds a mapping or a list, since any plain text is a valid YAML string.
+/// Returns None if the data isn't valid in either format.
+/// ### Example
+/// ```rust
+/// use rsconfig::files::{self, ConfigFormat};
+///
+/// assert_eq!(files::detect_format(" {\"port\": 80}"), Some(ConfigFormat::Json));
+/// assert_eq!(files::detect_format("port: 80"), Some(ConfigFormat::Yaml));
+/// assert_eq!(files::detect_format("just some text"), None);
+/// ```
+pub fn detect_format(data: &str) -> Option<ConfigFormat> {
+    match sniff(data).ok()? {
+        Sniffed::Json(_) => Some(ConfigFormat::Json),
+        Sniffed::Yaml(_) => Some(ConfigFormat::Yaml),
+    }
+}
+
+// keeps the parsed data, so loading doesn't parse it twice
+enum Sniffed {
+    Json(Value),
+    Yaml(Vec<Yaml>),
+}
+
+type SniffFailures = Vec<(&'static str, Box<dyn Error + Send + Sync>)>;
+
+fn sniff(data: &str) -> Result<Sniffed, SniffFailures> {
+    let mut failures: SniffFailures = Vec::new();
+
+    if data.trim_start().starts_with(['{', '[']) {
+        match serde_json::from_str(data) {
+            Ok(val) => return Ok(Sniffed::Json(val)),
+            Err(e) => failures.push(("JSON", e.into())),
+        }
+    }
+
+    match YamlLoader::load_from_str(data) {
+        Ok(yaml) if matches!(yaml.first(), Some(Yaml::Hash(_) | Yaml::Array(_))) => {
+            return Ok(Sniffed::Yaml(yaml))
+        }
+        Ok(yaml) if yaml.is_empty() => failures.push(("YAML", "there is no data".into())),
+        Ok(_) => failures.push(("YAML", "it is plain text, not a mapping or a list".into())),
+        Err(e) => failures.push(("YAML", e.into())),
+    }
+
+    Err(failures)
+}
+
+// the extension as written, or `env` for dotenv files
+fn format_extension(path: &Path) -> Result<Cow<'_, str>, ConfigError> {
+    // `.env` is a file name rather than an extension
+    let is_dotenv = path
+        .file_name()
+        .is_some_and(|name| name.to_string_lossy().trim().eq_ignore_ascii_case(".env"));
+
+    match path
+        .extension()
+        .map(|extension| extension.to_string_lossy())
+    {
+        _ if is_dotenv => Ok("env".into()),
+        Some(extension) if !extension.trim().is_empty() => Ok(extension),
+        _ => Err(ConfigError::NoExtension {
+            path: path.into(),
+            supported: supported_extensions(),
+        }),
+    }
+}
+
+/// Saves a configuration struct to a file, choosing the format from its extension like [`load_from_file`].
+/// YAML and JSON are written with `save_yaml` and `save_json`; `.json5` files are written as JSON, which JSON5 can read.
+/// The other formats `load_from_file` understands can't be saved through FileConfig,
+/// and return [`ConfigError::UnsupportedFormat`].
+/// Missing parent directories are created, use [`save_to_file_with`] to turn that off.
+/// Returns an error naming the file if the extension isn't recognized or the file can't be written.
+/// ### Example
+/// ```rust,no_run
+/// # use rsconfig::{files, FileConfig};
+/// # fn example<T: FileConfig>() -> Result<(), rsconfig::ConfigError> {
+/// let config: T = files::load_from_file("config.yml")?;
+/// files::save_to_file(&config, "config.json")?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn save_to_file<T: FileConfig>(config: &T, path: impl AsRef<Path>) -> Result<(), ConfigError> {
+    save_to_file_with(config, path, SaveOptions::new())
+}
+
+/// Options for [`save_to_file_with`].
+/// ### Example
+/// ```rust,no_run
+/// # use rsconfig::{files::{self, SaveOptions}, FileConfig};
+/// # fn example<T: FileConfig>(config: &T) -> Result<(), rsconfig::ConfigError> {
+/// // the directory is created by the installer, so a missing one means something is wrong
+/// files::save_to_file_with(config, "/etc/myapp/config.yml", SaveOptions::new().create_dirs(false))?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SaveOptions {
+    create_dirs: bool,
+    backup: Option<BackupPolicy>,
+    #[cfg(feature = "locking")]
+    lock: Option<LockMode>,
+}
+
+impl SaveOptions {
+    /// The options [`save_to_file`] uses.
+    pub fn new() -> Self {
+        Self {
+            create_dirs: true,
+            backup: None,
+            #[cfg(feature = "locking")]
+            lock: None,
+        }
+    }
+
+    /// Whether to create the file's missing parent directories before saving, true by default.
+    pub fn create_dirs(mut self, create_dirs: bool) -> Self {
+        self.create_dirs = create_dirs;
+        self
+    }
+
+    /// Copies the existing file before it's overwritten, following `policy`. Off by default.
+    pub fn backup(mut self, policy: BackupPolicy) -> Self {
+        self.backup = Some(policy);
+        self
+    }
+
+    /// Holds the file's exclusive lock while saving, see [`save_to_file_locked`]. Off by default.
+    #[cfg(feature = "locking")]
+    pub fn lock(mut self, mode: LockMode) -> Self {
+        self.lock = Some(mode);
+        self
+    }
+}
+
+impl Default for SaveOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// How [`save_with_backup`] keeps a copy of the file it's about to overwrite.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BackupPolicy {
+    /// Copies the file to its path with the suffix added, like `config.yml.bak`, replacing the previous backup
+    Suffix(String),
+    /// Keeps up to this many backups, from `config.yml.bak.1` (the newest) to `config.yml.bak.N` (the oldest)
+    Rotate(usize),
+}
+
+impl Default for BackupPolicy {
+    fn default() -> Self {
+        Self::Suffix(".bak".to_string())
+    }
+}
+
+/// Same as [`save_to_file`], but copies the existing file first so hand edits aren't lost.
+/// No backup is made if the file doesn't exist yet.
+/// ### Example
+/// ```rust,no_run
+/// # use rsconfig::{files::{self, BackupPolicy}, FileConfig};
+/// # fn example<T: FileConfig>(config: &T) -> Result<(), rsconfig::ConfigError> {
+/// // keeps config.yml.bak.1 to config.yml.bak.3
+/// files::save_with_backup(config, "config.yml", BackupPolicy::Rotate(3))?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn save_with_backup<T: FileConfig>(
+    config: &T,
+    path: impl AsRef<Path>,
+    policy: BackupPolicy,
+) -> Result<(), ConfigError> {
+    save_to_file_with(config, path, SaveOptions::new().backup(policy))
+}
+
+/// Same as [`save_to_file`], with options for how the file is saved.
+pub fn save_to_file_with<T: FileConfig>(
+    config: &T,
+    path: impl AsRef<Path>,
+    options: SaveOptions,
+) -> Result<(), ConfigError> {
+    let path = path.as_ref();
+    let save = save_method::<T>(path)?;
+
+    // the save methods take a &str
+    let utf8_path = path_str(path).map_err(|e| ConfigError::write(path, e))?;
+
+    if options.create_dirs {
+        create_parent_dirs(path)?;
+    }
+
+    #[cfg(feature = "locking")]
+    let _lock = match options.lock {
+        Some(mode) => lock(path, mode, true)?,
+        None => None,
+    };
+
+    if let Some(policy) = &options.backup {
+        backup(path, policy)?;
+    }
+
+    save(config, utf8_path).map_err(|e| ConfigError::write(path, e))
+}
+
+fn create_parent_dirs(path: &Path) -> Result<(), ConfigError> {
+    match path.parent() {
+        // `config.yml` has an empty parent, which is the current directory
+        Some(parent) if !parent.as_os_str().is_empty() => {
+            fs::create_dir_all(parent).map_err(|e| ConfigError::write(path, e))
+        }
+        _ => Ok(()),
+    }
+}
+
+type SaveMethod<T> = fn(&T, &str) -> io::Result<()>;
+
+// the save method for the path's extension, keep in sync with supported_extensions
+fn save_method<T: FileConfig>(path: &Path) -> Result<SaveMethod<T>, ConfigError> {
+    let extension = format_extension(path)?;
+
+    let unsupported = |format| {
+        Err(ConfigError::UnsupportedFormat {
+            path: path.into(),
+            format,
+        })
+    };
+
+    match extension.trim().to_lowercase().as_str() {
+        "yaml" | "yml" => Ok(T::save_yaml),
+        "json" => Ok(T::save_json),
+        #[cfg(feature = "json5")]
+        "json5" => Ok(T::save_json),
+        "toml" => unsupported("TOML"),
+        "ini" => unsupported("INI"),
+        "properties" => unsupported("properties"),
+        #[cfg(feature = "xml")]
+        "xml" => unsupported("XML"),
+        #[cfg(feature = "rmp")]
+        "msgpack" | "mp" => unsupported("MessagePack"),
+        #[cfg(feature = "cbor")]
+        "cbor" => unsupported("CBOR"),
+        #[cfg(feature = "bincode")]
+        "bin" => unsupported("binary"),
+        "env" => unsupported("dotenv"),
+        _ => Err(ConfigError::UnsupportedExtension {
+            path: path.into(),
+            extension: extension.into_owned(),
+            supported: supported_extensions(),
+        }),
+    }
+}
+
+// the file is copied rather than moved, so it's still there if saving fails
+fn backup(path: &Path, policy: &BackupPolicy) -> Result<(), ConfigError> {
+    if !path.exists() {
+        return Ok(());
+    }
+
+    let with_suffix = |suffix: &str| {
+        let mut backup = path.as_os_str().to_owned();
+        backup.push(suffix);
+        PathBuf::from(backup)
+    };
+
+    let backup = match policy {
+        BackupPolicy::Suffix(suffix) => with_suffix(suffix),
+        BackupPolicy::Rotate(0) => return Ok(()),
+        BackupPolicy::Rotate(count) => {
+            // shift every backup one older, the oldest is overwritten
+            for n in (1..*count).rev() {
+                let older = with_suffix(&format!(".bak.{}", n));
+
+                if older.exists() {
+                    let newer = with_suffix(&format!(".bak.{}", n + 1));
+                    fs::rename(&older, &newer).map_err(|e| ConfigError::write(newer, e))?;
+                }
+            }
+
+            with_suffix(".bak.1")
+        }
+    };
+
+    fs::copy(path, &backup)
+        .map(drop)
+        .map_err(|e| ConfigError::write(backup, e))
 }
 
-/// Loads a configuration struct from a file.
+/// How the locking loaders and savers wait for a lock held by another process.
+#[cfg(feature = "locking")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LockMode {
+    /// Wait until the lock is free
+    Block,
+    /// Return [`ConfigError::Locked`] right away if the lock is held
+    Try,
+}
+
+/// Same as [`load_from_file`], holding a shared lock on the file while it's read,
+/// so it's never read halfway through a [`save_to_file_locked`].
+/// The lock is advisory: it only keeps out other processes that lock the file too.
+///
+/// Locks are taken on a `.lock` file next to the config (`config.yml.lock`), which is left in place.
+/// If the lock file can't be created (like in a read-only directory) or the filesystem doesn't support locks,
+/// the file is loaded without a lock.
+/// ### Example
+/// ```rust,no_run
+/// # use rsconfig::{files::{self, LockMode}, ConfigError, FileConfig};
+/// # fn example<T: FileConfig>() -> Result<(), ConfigError> {
+/// match files::load_from_file_locked::<T>("shared.yml", LockMode::Try) {
+///     Ok(config) => { /* ... */ }
+///     Err(ConfigError::Locked { .. }) => eprintln!("another instance is saving the config"),
+///     Err(e) => return Err(e),
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[cfg(feature = "locking")]
+pub fn load_from_file_locked<T: FileConfig>(
+    path: impl AsRef<Path>,
+    mode: LockMode,
+) -> Result<T, ConfigError> {
+    let path = path.as_ref();
+    let _lock = lock(path, mode, false)?;
+
+    try_load_from_file(path)
+}
+
+/// Same as [`save_to_file`], holding an exclusive lock on the file while it's written,
+/// so two processes saving at once can't interleave their writes.
+/// Locks work the same way as in [`load_from_file_locked`], except a lock file that can't be created is an error.
+#[cfg(feature = "locking")]
+pub fn save_to_file_locked<T: FileConfig>(
+    config: &T,
+    path: impl AsRef<Path>,
+    mode: LockMode,
+) -> Result<(), ConfigError> {
+    save_to_file_with(config, path, SaveOptions::new().lock(mode))
+}
+
+// the returned file holds the lock until it's dropped, None means the file isn't locked
+#[cfg(feature = "locking")]
+fn lock(path: &Path, mode: LockMode, exclusive: bool) -> Result<Option<File>, ConfigError> {
+    use std::fs::{OpenOptions, TryLockError};
+
+    // locking the config itself would break on windows, where locks keep other handles from writing
+    let mut lock_path = path.as_os_str().to_owned();
+    lock_path.push(".lock");
+    let lock_path = PathBuf::from(lock_path);
+
+    let file = match OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(&lock_path)
+    {
+        Ok(file) => file,
+        Err(_) if !exclusive => return Ok(None),
+        Err(e) => return Err(ConfigError::write(lock_path, e)),
+    };
+
+    let result = match (mode, exclusive) {
+        (LockMode::Block, true) => file.lock().map_err(TryLockError::Error),
+        (LockMode::Block, false) => file.lock_shared().map_err(TryLockError::Error),
+        (LockMode::Try, true) => file.try_lock(),
+        (LockMode::Try, false) => file.try_lock_shared(),
+    };
+
+    match result {
+        Ok(()) => Ok(Some(file)),
+        Err(TryLockError::WouldBlock) => Err(ConfigError::Locked { path: path.into() }),
+        Err(TryLockError::Error(e)) if locks_unsupported(&e) => Ok(None),
+        Err(TryLockError::Error(e)) => Err(ConfigError::io(lock_path, e)),
+    }
+}
+
+#[cfg(feature = "locking")]
+fn locks_unsupported(e: &io::Error) -> bool {
+    // ENOLCK, which some network filesystems return, has a different number on linux and the BSDs
+    #[cfg(unix)]
+    let no_locks = e.raw_os_error() == Some(libc::ENOLCK);
+    #[cfg(not(unix))]
+    let no_locks = false;
+
+    e.kind() == io::ErrorKind::Unsupported || no_locks
+}
+
+// keep in sync with the matches in load_from_file_with, read_value and save_method
+fn supported_extensions() -> Vec<&'static str> {
+    let mut supported = vec!["yaml", "yml", "json"];
+
+    #[cfg(feature = "json5")]
+    supported.push("json5");
+
+    supported.extend(["toml", "ini", "properties"]);
+
+    #[cfg(feature = "xml")]
+    supported.push("xml");
+
+    #[cfg(feature = "rmp")]
+    supported.extend(["msgpack", "mp"]);
+
+    #[cfg(feature = "cbor")]
+    supported.push("cbor");
+
+    #[cfg(feature = "bincode")]
+    supported.push("bin");
+
+    supported.push("env");
+    supported
+}
+
+/// Loads a configuration struct from a file descriptor, such as a pipe or a file opened by the parent process.
+/// The descriptor is duplicated before reading to EOF, so the caller's descriptor is never closed.
+/// Errors name the descriptor in place of a path, like `file descriptor 3`.
 /// Output type must impl FileConfig
-pub fn load_from_file<T: FileConfig>(path: &str) -> Result<T, ()> {
-    let p: Vec<&str> = path.split(".").collect();
+/// ### Example
+/// ```rust,no_run
+/// # use rsconfig::{files::{self, ConfigFormat}, FileConfig};
+/// # use std::os::fd::BorrowedFd;
+/// # fn example<T: FileConfig>() -> Result<(), rsconfig::ConfigError> {
+/// // started with `myapp --config-fd 3`
+/// let fd = unsafe { BorrowedFd::borrow_raw(3) };
+/// let config: T = files::load_from_fd(fd, ConfigFormat::Yaml)?;
+/// # Ok(())
+/// # }
+/// ```
+#[cfg(unix)]
+pub fn load_from_fd<T: FileConfig>(fd: impl AsFd, format: ConfigFormat) -> Result<T, ConfigError> {
+    let fd = fd.as_fd();
+    let name = PathBuf::from(format!("file descriptor {}", fd.as_raw_fd()));
+
+    let file = fd
+        .try_clone_to_owned()
+        .map_err(|e| ConfigError::io(&name, e))?;
+
+    read_from_descriptor(File::from(file), format, &name)
+}
+
+/// Loads a configuration struct from a handle, such as a pipe or a file opened by the parent process.
+/// The handle is duplicated before reading to EOF, so the caller's handle is never closed.
+/// Errors name the handle in place of a path.
+/// Output type must impl FileConfig
+#[cfg(windows)]
+pub fn load_from_handle<T: FileConfig>(
+    handle: impl AsHandle,
+    format: ConfigFormat,
+) -> Result<T, ConfigError> {
+    let handle = handle.as_handle();
+    let name = PathBuf::from(format!("handle {:?}", handle.as_raw_handle()));
+
+    let file = handle
+        .try_clone_to_owned()
+        .map_err(|e| ConfigError::io(&name, e))?;
+
+    read_from_descriptor(File::from(file), format, &name)
+}
+
+#[cfg(any(unix, windows))]
+fn read_from_descriptor<T: FileConfig>(
+    mut file: File,
+    format: ConfigFormat,
+    name: &Path,
+) -> Result<T, ConfigError> {
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes)
+        .map_err(|e| ConfigError::io(name, e))?;
+
+    if bytes.is_empty() {
+        return Err(ConfigError::io(
+            name,
+            io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "no data, it may have been read already",
+            ),
+        ));
+    }
+
+    // decoded like files read by path, so a byte order mark or UTF-16 doesn't break parsing
+    let data = decode_text(&bytes).map_err(|e| ConfigError::io(name, e))?;
+
+    match format {
+        ConfigFormat::Yaml => parse_yaml(&data, name).map(T::from_yaml),
+        ConfigFormat::Json => parse_json(&data, name).map(T::from_json),
+    }
+}
+
+/// Saves several files together, so related configs aren't left half-written by a failure.
+/// Committing happens in two phases:
+/// 1. every staged file is written to a temporary file next to its target and synced to disk;
+///    if anything fails here, the temporary files are removed and no target is touched
+/// 2. each temporary file is renamed over its target, in the order it was staged
+///
+/// Renames are atomic one file at a time, but not across files. If a rename fails,
+/// the remaining files are still renamed and the error lists which files were committed and which weren't.
+/// If the process dies between renames, every file still holds either its old or its new contents,
+/// and committing the same files again finishes the job. The dead process's temporary files, named
+/// `.<file name>.<pid>.<n>.rsconfig-tmp`, are left next to their targets and can be deleted.
+/// ### Example
+/// ```rust,no_run
+/// # use rsconfig::{files::{self, ConfigFormat}, FileConfig};
+/// # fn example<T: FileConfig>(config: &T) -> Result<(), files::TransactionError> {
+/// files::Transaction::new()
+///     .stage_config(config, "config.yml", ConfigFormat::Yaml)
+///     .stage("cache/manifest.txt", "v2\n")
+///     .commit()
+/// # }
+/// ```
+pub struct Transaction<'a> {
+    staged: Vec<(PathBuf, Contents<'a>)>,
+    rename: Rename<'a>,
+}
+
+type Render<'a> = Box<dyn FnOnce(&str) -> io::Result<()> + 'a>;
+
+type Rename<'a> = Box<dyn FnMut(&Path, &Path) -> io::Result<()> + 'a>;
+
+enum Contents<'a> {
+    Bytes(Vec<u8>),
+    Render(Render<'a>),
+}
+
+/// What happened to one file of a partially committed [`Transaction`].
+#[derive(Debug)]
+pub enum FileState {
+    /// The new contents replaced the file
+    Committed,
+    /// The file wasn't replaced and still has its old contents
+    Failed(io::Error),
+}
+
+/// Error returned when a [`Transaction`] can't be committed.
+#[derive(Debug)]
+pub enum TransactionError {
+    /// Writing a temporary file failed, so no file was changed
+    Staging {
+        /// Target path of the file that couldn't be written
+        path: PathBuf,
+        /// The underlying error
+        error: io::Error,
+    },
+    /// Some files were replaced and others weren't, listed in staging order
+    Partial(Vec<(PathBuf, FileState)>),
+}
+
+impl fmt::Display for TransactionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Staging { path, error } => write!(
+                f,
+                "Failed to stage {}, no files were changed: {}",
+                path.display(),
+                error
+            ),
+            Self::Partial(files) => {
+                write!(f, "Transaction partially committed:")?;
+
+                for (path, state) in files {
+                    match state {
+                        FileState::Committed => write!(f, " {} (committed);", path.display())?,
+                        FileState::Failed(e) => {
+                            write!(f, " {} (not committed: {});", path.display(), e)?
+                        }
+                    }
+                }
+
+                Ok(())
+            }
+        }
+    }
+}
+
+impl Error for TransactionError {}
+
+impl Default for Transaction<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a> Transaction<'a> {
+    /// Creates an empty transaction.
+    pub fn new() -> Self {
+        Self {
+            staged: Vec::new(),
+            rename: Box::new(|temp, path| fs::rename(temp, path)),
+        }
+    }
+
+    // lets tests fail or crash a commit partway through its renames
+    #[cfg(test)]
+    pub(crate) fn with_rename(rename: impl FnMut(&Path, &Path) -> io::Result<()> + 'a) -> Self {
+        Self {
+            staged: Vec::new(),
+            rename: Box::new(rename),
+        }
+    }
+
+    /// Stages raw contents to be written to a path.
+    pub fn stage(mut self, path: impl Into<PathBuf>, contents: impl Into<Vec<u8>>) -> Self {
+        self.staged
+            .push((path.into(), Contents::Bytes(contents.into())));
+        self
+    }
+
+    /// Stages a function that writes the file itself, given the temporary path to write to.
+    /// Useful for reusing save methods like `save_yaml`.
+    pub fn stage_with(
+        mut self,
+        path: impl Into<PathBuf>,
+        render: impl FnOnce(&str) -> io::Result<()> + 'a,
+    ) -> Self {
+        self.staged
+            .push((path.into(), Contents::Render(Box::new(render))));
+        self
+    }
+
+    /// Stages a config to be saved with its `save_yaml` or `save_json` method.
+    pub fn stage_config<T: FileConfig>(
+        self,
+        config: &'a T,
+        path: impl Into<PathBuf>,
+        format: ConfigFormat,
+    ) -> Self {
+        self.stage_with(path, move |temp| match format {
+            ConfigFormat::Yaml => config.save_yaml(temp),
+            ConfigFormat::Json => config.save_json(temp),
+        })
+    }
+
+    /// Writes and syncs every staged file, then renames them into place.
+    pub fn commit(mut self) -> Result<(), TransactionError> {
+        let mut temps: Vec<(PathBuf, PathBuf)> = Vec::new();
+
+        for (path, contents) in self.staged {
+            let temp = temp_path(&path);
+
+            if let Err(error) = write_synced(&temp, contents) {
+                let _ = fs::remove_file(&temp);
+                for (_, temp) in &temps {
+                    let _ = fs::remove_file(temp);
+                }
+
+                return Err(TransactionError::Staging { path, error });
+            }
+
+            temps.push((path, temp));
+        }
+
+        let mut failed = false;
+        let states: Vec<(PathBuf, FileState)> = temps
+            .into_iter()
+            .map(|(path, temp)| match (self.rename)(&temp, &path) {
+                Ok(()) => {
+                    sync_parent(&path);
+                    (path, FileState::Committed)
+                }
+                Err(e) => {
+                    failed = true;
+                    let _ = fs::remove_file(&temp);
+                    (path, FileState::Failed(e))
+                }
+            })
+            .collect();
+
+        if failed {
+            return Err(TransactionError::Partial(states));
+        }
+
+        Ok(())
+    }
+}
+
+// temporary files live next to the target so the rename stays on one filesystem,
+// and the counter keeps threads saving the same file from sharing one
+pub(crate) fn temp_path(path: &Path) -> PathBuf {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let n = COUNTER.fetch_add(1, Ordering::Relaxed);
+
+    path.with_file_name(format!(".{}.{}.{}.rsconfig-tmp", name, process::id(), n))
+}
+
+fn write_synced(temp: &Path, contents: Contents) -> io::Result<()> {
+    match contents {
+        Contents::Bytes(bytes) => fs::write(temp, bytes)?,
+        Contents::Render(render) => {
+            let temp = temp.to_str().ok_or_else(|| {
+                io::Error::new(io::ErrorKind::InvalidInput, "Path is not valid UTF-8")
+            })?;
+
+            render(temp)?;
+        }
+    }
+
+    File::open(temp)?.sync_all()
+}
+
+// makes the rename itself durable, where the platform allows syncing directories
+fn sync_parent(path: &Path) {
+    #[cfg(unix)]
+    if let Some(parent) = path.parent() {
+        let parent = if parent.as_os_str().is_empty() {
+            Path::new(".")
+        } else {
+            parent
+        };
+
+        if let Ok(dir) = File::open(parent) {
+            let _ = dir.sync_all();
+        }
+    }
+
+    #[cfg(not(unix))]
+    let _ = path;
+}
+
+/// Writes a copy of a config file with sensitive values replaced, for attaching to bug reports.
+/// Supports YAML, JSON and TOML files, writing the same format to `dst`. Comments and formatting aren't kept.
+/// The format is chosen from the extension like [`load_from_file`], so `CONFIG.YML` is read as YAML.
+/// YAML keys have to be strings, numbers, booleans or null, since the values are scrubbed as JSON.
+/// Returns the scrubbed value that was written and the replacements that were made, which should stay private.
+/// A YAML file with several documents gives an array of them, and an empty one gives null.
+/// See [`anonymize::scrub`] for what gets replaced.
+/// ### Example
+/// ```rust,no_run
+/// # use rsconfig::{anonymize::ScrubRules, files};
+/// let (_, map) = files::anonymize_file("config.yml", "config.shared.yml", &ScrubRules::default())?;
+/// println!("replaced {} values", map.len());
+/// # Ok::<(), rsconfig::ConfigError>(())
+/// ```
+#[cfg(feature = "anonymize")]
+pub fn anonymize_file(
+    src: impl AsRef<Path>,
+    dst: impl AsRef<Path>,
+    rules: &anonymize::ScrubRules,
+) -> Result<(Value, anonymize::ScrubMap), ConfigError> {
+    let src = src.as_ref();
+    let dst = dst.as_ref();
+    let unwritable = |format, e: &dyn fmt::Display| {
+        ConfigError::write(
+            dst,
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Failed to write {}: {}", format, e),
+            ),
+        )
+    };
+
+    let (output, value, map) = match format_extension(src)?.trim().to_lowercase().as_str() {
+        "yaml" | "yml" => {
+            let docs = read_yaml(src)?;
+
+            let mut output = String::new();
+            let mut values = Vec::new();
+            let mut map = anonymize::ScrubMap::default();
+
+            for doc in &docs {
+                let doc = yaml_to_json(doc).map_err(|e| ConfigError::parse(src, "YAML", e))?;
+                let (scrubbed, doc_map) = anonymize::scrub(&doc, rules);
+                map.extend(doc_map);
+
+                let mut emitter = yaml_rust::YamlEmitter::new(&mut output);
+                emitter
+                    .dump(&json_to_yaml(&scrubbed))
+                    .map_err(|e| unwritable("YAML", &format!("{:?}", e)))?;
+                output.push('\n');
+
+                values.push(scrubbed);
+            }
+
+            let value = match values.len() {
+                0 => Value::Null,
+                1 => values.remove(0),
+                _ => Value::Array(values),
+            };
+
+            (output, value, map)
+        }
+        "json" => {
+            let (scrubbed, map) = anonymize::scrub(&read_json(src)?, rules);
+            let output =
+                serde_json::to_string_pretty(&scrubbed).map_err(|e| unwritable("JSON", &e))?;
+
+            (output, scrubbed, map)
+        }
+        "toml" => {
+            let value = serde_json::to_value(read_toml(src)?)
+                .map_err(|e| ConfigError::parse(src, "TOML", e))?;
+            let (scrubbed, map) = anonymize::scrub(&value, rules);
+
+            let output = toml::Value::try_from(&scrubbed)
+                .and_then(|value| toml::to_string(&value))
+                .map_err(|e| unwritable("TOML", &e))?;
+
+            (output, scrubbed, map)
+        }
+        extension => {
+            return Err(ConfigError::UnsupportedExtension {
+                path: src.into(),
+                extension: extension.to_string(),
+                supported: vec!["yaml", "yml", "json", "toml"],
+            })
+        }
+    };
+
+    fs::write(dst, output).map_err(|e| ConfigError::write(dst, e))?;
+
+    Ok((value, map))
+}
+
+// JSON keys are strings, so keys that aren't scalars, or that become the same string (like `1` and `"1"`), are errors
+fn yaml_to_json(yaml: &Yaml) -> Result<Value, String> {
+    Ok(match yaml {
+        Yaml::Real(real) => real
+            .parse::<f64>()
+            .ok()
+            .and_then(serde_json::Number::from_f64)
+            .map(Value::Number)
+            .unwrap_or_else(|| Value::String(real.clone())),
+        Yaml::Integer(i) => Value::from(*i),
+        Yaml::String(s) => Value::String(s.clone()),
+        Yaml::Boolean(b) => Value::Bool(*b),
+        Yaml::Array(values) => {
+            Value::Array(values.iter().map(yaml_to_json).collect::<Result<_, _>>()?)
+        }
+        Yaml::Hash(map) => {
+            let mut object = serde_json::Map::new();
+
+            for (key, value) in map {
+                let key = match key {
+                    Yaml::String(s) => s.clone(),
+                    Yaml::Integer(i) => i.to_string(),
+                    Yaml::Real(r) => r.clone(),
+                    Yaml::Boolean(b) => b.to_string(),
+                    Yaml::Null => "null".to_string(),
+                    key => {
+                        return Err(format!(
+                            "key {:?} isn't a string, number, boolean or null",
+                            key
+                        ))
+                    }
+                };
+
+                if object.contains_key(&key) {
+                    return Err(format!("more than one key is written as `{}`", key));
+                }
+
+                object.insert(key, yaml_to_json(value)?);
+            }
+
+            Value::Object(object)
+        }
+        _ => Value::Null,
+    })
+}
 
-    match *p.last().unwrap() {
-        "yaml" | "yml" => Ok(load_from_yaml(path)),
-        "json" => Ok(load_from_json(path)),
-        _ => Err(()),
+#[cfg(feature = "anonymize")]
+fn json_to_yaml(value: &Value) -> Yaml {
+    match value {
+        Value::Null => Yaml::Null,
+        Value::Bool(b) => Yaml::Boolean(*b),
+        Value::Number(n) => match n.as_i64() {
+            Some(i) => Yaml::Integer(i),
+            None => Yaml::Real(n.to_string()),
+        },
+        Value::String(s) => Yaml::String(s.clone()),
+        Value::Array(values) => Yaml::Array(values.iter().map(json_to_yaml).collect()),
+        Value::Object(map) => Yaml::Hash(
+            map.iter()
+                .map(|(key, value)| (Yaml::String(key.clone()), json_to_yaml(value)))
+                .collect(),
+        ),
     }
 }
@@ -0,0 +1,172 @@
+use std::{error::Error, fmt};
+
+/// Parsed contents of an INI file, keeping sections and keys in file order.
+/// Keys that appear before the first `[section]` header belong to the global section, named `""`.
+/// Repeated keys are all kept; [`Ini::get`] returns the last one, like later lines overriding earlier ones.
+/// Lines starting with `;` or `#` are comments. Values are kept as written (trimmed), so inline `;` and `#` are part of the value.
+/// ### Example
+/// ```rust
+/// use rsconfig::ini::Ini;
+///
+/// let ini: Ini = "name = app\n[server]\nport = 8080\n".parse().unwrap();
+///
+/// assert_eq!(ini.get("", "name"), Some("app"));
+/// assert_eq!(ini.get("server", "port"), Some("8080"));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Ini {
+    sections: Vec<(String, Vec<(String, String)>)>,
+}
+
+impl Ini {
+    /// Creates an empty INI document.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the last value set for a key in a section.
+    pub fn get(&self, section: &str, key: &str) -> Option<&str> {
+        self.get_all(section, key).last().copied()
+    }
+
+    /// Returns every value set for a key in a section, in file order.
+    pub fn get_all(&self, section: &str, key: &str) -> Vec<&str> {
+        self.section(section)
+            .map(|entries| {
+                entries
+                    .iter()
+                    .filter(|(k, _)| k == key)
+                    .map(|(_, v)| v.as_str())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Returns the entries of a section, in file order.
+    pub fn section(&self, name: &str) -> Option<&[(String, String)]> {
+        self.sections
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, entries)| entries.as_slice())
+    }
+
+    /// Iterates over the section names, in file order. The global section is included if it has entries.
+    pub fn sections(&self) -> impl Iterator<Item = &str> {
+        self.sections.iter().map(|(name, _)| name.as_str())
+    }
+
+    fn section_mut(&mut self, name: &str) -> &mut Vec<(String, String)> {
+        let index = match self.sections.iter().position(|(n, _)| n == name) {
+            Some(index) => index,
+            None => {
+                // the global section always comes first so it can be written without a header
+                let index = if name.is_empty() {
+                    0
+                } else {
+                    self.sections.len()
+                };
+                self.sections.insert(index, (name.to_string(), Vec::new()));
+                index
+            }
+        };
+
+        &mut self.sections[index].1
+    }
+
+    /// Sets a key in a section, replacing every earlier value for it.
+    pub fn set(&mut self, section: &str, key: &str, value: impl Into<String>) {
+        let entries = self.section_mut(section);
+        entries.retain(|(k, _)| k != key);
+        entries.push((key.to_string(), value.into()));
+    }
+
+    /// Adds a value for a key in a section, keeping earlier values.
+    pub fn add(&mut self, section: &str, key: &str, value: impl Into<String>) {
+        self.section_mut(section)
+            .push((key.to_string(), value.into()));
+    }
+}
+
+/// Error returned when an INI document can't be parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IniParseError {
+    /// Line the error was found on, starting from 1
+    pub line: usize,
+    /// Description of what was wrong with the line
+    pub message: String,
+}
+
+impl fmt::Display for IniParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl Error for IniParseError {}
+
+impl std::str::FromStr for Ini {
+    type Err = IniParseError;
+
+    fn from_str(data: &str) -> Result<Self, Self::Err> {
+        let mut ini = Ini::new();
+        let mut section = String::new();
+
+        for (i, line) in data.lines().enumerate() {
+            let line = line.trim();
+            let error = |message: &str| IniParseError {
+                line: i + 1,
+                message: message.to_string(),
+            };
+
+            if line.is_empty() || line.starts_with(';') || line.starts_with('#') {
+                continue;
+            }
+
+            if let Some(header) = line.strip_prefix('[') {
+                let name = header
+                    .strip_suffix(']')
+                    .ok_or_else(|| error("section header is missing a closing `]`"))?;
+
+                section = name.trim().to_string();
+                ini.section_mut(&section);
+                continue;
+            }
+
+            let Some(separator) = line.find(['=', ':']) else {
+                return Err(error("expected `key = value`"));
+            };
+
+            let key = line[..separator].trim();
+            if key.is_empty() {
+                return Err(error("key is empty"));
+            }
+
+            ini.add(&section, key, line[separator + 1..].trim());
+        }
+
+        Ok(ini)
+    }
+}
+
+impl fmt::Display for Ini {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut first = true;
+
+        for (name, entries) in &self.sections {
+            if !name.is_empty() {
+                if !first {
+                    writeln!(f)?;
+                }
+                writeln!(f, "[{}]", name)?;
+            }
+
+            for (key, value) in entries {
+                writeln!(f, "{} = {}", key, value)?;
+            }
+
+            first = false;
+        }
+
+        Ok(())
+    }
+}
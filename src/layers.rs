@@ -0,0 +1,350 @@
+use crate::{
+    env::EnvError,
+    requirements::{self, Requirement, RequirementFailure},
+    *,
+};
+
+use serde_json::Map;
+
+use std::{
+    collections::BTreeMap,
+    error::Error,
+    fmt, fs, io,
+    marker::PhantomData,
+    path::{Path, PathBuf},
+};
+
+/// Where a value in a [`Merged`] config came from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Layer {
+    /// The values given to [`ConfigBuilder::defaults`]
+    Defaults,
+    /// A config file
+    File(PathBuf),
+    /// The environment variables starting with this prefix
+    Env(String),
+    /// The commandline arguments
+    Args,
+}
+
+impl fmt::Display for Layer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Defaults => write!(f, "defaults"),
+            Self::File(path) => write!(f, "file {}", path.display()),
+            Self::Env(prefix) => write!(f, "environment variables {}*", prefix),
+            Self::Args => write!(f, "commandline arguments"),
+        }
+    }
+}
+
+/// Error returned when one of a [`ConfigBuilder`]'s layers can't be loaded.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum LayerError {
+    /// The defaults couldn't be written as JSON through `JsonConfig::write_json` or `JsonConfig::save_json`
+    Defaults(io::Error),
+    /// A config file couldn't be loaded
+    File(ConfigError),
+    /// The prefixed environment variables couldn't be turned into values
+    Env(EnvError),
+    /// A commandline flag couldn't be turned into a value
+    Args {
+        /// The flag, as it was passed
+        flag: String,
+        /// Description of what was wrong with the flag
+        message: String,
+    },
+    /// The merged config didn't meet requirements added with [`ConfigBuilder::require`]
+    Requirements(Vec<RequirementFailure>),
+}
+
+impl fmt::Display for LayerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Defaults(e) => write!(f, "Failed to write the defaults as JSON: {}", e),
+            Self::File(e) => write!(f, "{}", e),
+            Self::Env(e) => write!(f, "Invalid environment variable {}", e),
+            Self::Args { flag, message } => write!(f, "Invalid argument {}: {}", flag, message),
+            Self::Requirements(failures) => {
+                write!(f, "Unmet requirements:")?;
+
+                for failure in failures {
+                    write!(f, "\n  {}", failure)?;
+                }
+
+                Ok(())
+            }
+        }
+    }
+}
+
+impl Error for LayerError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Defaults(e) => Some(e),
+            Self::File(e) => Some(e),
+            Self::Env(e) => Some(e),
+            Self::Args { .. } | Self::Requirements(_) => None,
+        }
+    }
+}
+
+impl From<ConfigError> for LayerError {
+    fn from(e: ConfigError) -> Self {
+        Self::File(e)
+    }
+}
+
+impl From<EnvError> for LayerError {
+    fn from(e: EnvError) -> Self {
+        Self::Env(e)
+    }
+}
+
+/// The merged values of a [`ConfigBuilder`], with the layer each top-level key came from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Merged {
+    /// The merged values, ready for `JsonConfig::from_json`
+    pub value: Value,
+    /// The last layer that set each top-level key, or part of it for keys holding objects
+    pub sources: BTreeMap<String, Layer>,
+}
+
+#[derive(Debug)]
+enum Source {
+    Defaults(io::Result<Value>),
+    File { path: PathBuf, optional: bool },
+    Env(String),
+    Args(Vec<String>),
+}
+
+/// Builds a config from layers of values, like defaults overridden by a config file,
+/// overridden by environment variables, overridden by commandline flags.
+/// Layers are added in priority order, so later layers win when they set the same key.
+/// Objects are merged key by key, and any other value replaces the one before it.
+///
+/// Commandline flags are read as `--name=value`, or `--name` for `true`. Dashes in the name become underscores,
+/// dots nest objects (`--db.port=5432`), and values are converted like environment variables in
+/// [`env::prefixed_value_from`]. Arguments that aren't flags are ignored, as is everything after `--`.
+/// ### Example
+/// ```rust,no_run
+/// # use rsconfig::{layers::ConfigBuilder, JsonConfig};
+/// # fn example<T: JsonConfig + Default>() -> Result<(), rsconfig::layers::LayerError> {
+/// let merged = ConfigBuilder::<T>::new()
+///     .defaults(T::default())
+///     .optional_file("/etc/myapp/config.yml")
+///     .file("config.yml")
+///     .env_prefix("MYAPP_")
+///     .args(std::env::args().collect())
+///     .merge()?;
+///
+/// for (key, layer) in &merged.sources {
+///     println!("{} from {}", key, layer);
+/// }
+///
+/// let config = T::from_json(merged.value);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct ConfigBuilder<T> {
+    sources: Vec<Source>,
+    requirements: Vec<Requirement>,
+    config: PhantomData<fn() -> T>,
+}
+
+impl<T: JsonConfig> ConfigBuilder<T> {
+    /// Creates a builder with no layers.
+    pub fn new() -> Self {
+        Self {
+            sources: Vec::new(),
+            requirements: Vec::new(),
+            config: PhantomData,
+        }
+    }
+
+    /// Adds a layer with the values of `defaults`, written through `JsonConfig::write_json`.
+    /// Types that don't implement `write_json` are saved with `save_json` to a temporary file and read back.
+    /// Usually the first layer, so everything else overrides it.
+    pub fn defaults(mut self, defaults: T) -> Self {
+        self.sources
+            .push(Source::Defaults(defaults_value(&defaults)));
+        self
+    }
+
+    /// Adds a layer with the values of a YAML, JSON or TOML file (JSON5 with the `json5` feature).
+    /// Merging fails if the file doesn't exist, use [`ConfigBuilder::optional_file`] for files that may be missing.
+    pub fn file(mut self, path: impl AsRef<Path>) -> Self {
+        self.sources.push(Source::File {
+            path: path.as_ref().into(),
+            optional: false,
+        });
+        self
+    }
+
+    /// Same as [`ConfigBuilder::file`], but the layer is skipped if the file doesn't exist.
+    pub fn optional_file(mut self, path: impl AsRef<Path>) -> Self {
+        self.sources.push(Source::File {
+            path: path.as_ref().into(),
+            optional: true,
+        });
+        self
+    }
+
+    /// Adds a layer with the environment variables starting with `prefix`, read by [`env::prefixed_value`] when merging.
+    pub fn env_prefix(mut self, prefix: &str) -> Self {
+        self.sources.push(Source::Env(prefix.to_string()));
+        self
+    }
+
+    /// Adds a layer with the flags in commandline arguments, see [`ConfigBuilder`] for how they're read.
+    pub fn args(mut self, args: Vec<String>) -> Self {
+        self.sources.push(Source::Args(args));
+        self
+    }
+
+    /// Adds requirements that are checked with [`requirements::verify`] once the layers are merged.
+    /// Merging fails with [`LayerError::Requirements`] listing every requirement that wasn't met.
+    pub fn require(mut self, reqs: impl IntoIterator<Item = Requirement>) -> Self {
+        self.requirements.extend(reqs);
+        self
+    }
+
+    /// Loads every layer and merges them, returning the first layer's error if one can't be loaded.
+    /// Any requirements are checked against the merged values afterwards.
+    pub fn merge(self) -> Result<Merged, LayerError> {
+        let mut merged = Merged {
+            value: Value::Object(Map::new()),
+            sources: BTreeMap::new(),
+        };
+
+        for source in self.sources {
+            let (layer, value) = match source {
+                Source::Defaults(value) => (Layer::Defaults, value.map_err(LayerError::Defaults)?),
+                Source::File { path, optional } => match files::read_value(&path) {
+                    Ok(value) => (Layer::File(path), value),
+                    Err(ConfigError::Io { source, .. })
+                        if optional && source.kind() == io::ErrorKind::NotFound =>
+                    {
+                        continue
+                    }
+                    Err(e) => return Err(e.into()),
+                },
+                Source::Env(prefix) => {
+                    let value = env::prefixed_value(&prefix)?;
+                    (Layer::Env(prefix), value)
+                }
+                Source::Args(args) => (Layer::Args, args_value(&args)?),
+            };
+
+            match &value {
+                Value::Object(map) => {
+                    for key in map.keys() {
+                        merged.sources.insert(key.clone(), layer.clone());
+                    }
+                }
+                // an empty file has nothing to override
+                Value::Null => continue,
+                _ => merged.sources.clear(),
+            }
+
+            merge_values(&mut merged.value, value);
+        }
+
+        let failures = requirements::verify(&merged.value, &self.requirements);
+        if !failures.is_empty() {
+            return Err(LayerError::Requirements(failures));
+        }
+
+        Ok(merged)
+    }
+
+    /// Merges the layers and creates the config from them with `JsonConfig::from_json`.
+    pub fn build(self) -> Result<T, LayerError> {
+        self.merge().map(|merged| T::from_json(merged.value))
+    }
+}
+
+impl<T: JsonConfig> Default for ConfigBuilder<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn defaults_value<T: JsonConfig>(defaults: &T) -> io::Result<Value> {
+    let mut data = Vec::new();
+
+    match defaults.write_json(&mut data) {
+        Ok(()) => {}
+        Err(e) if e.kind() == io::ErrorKind::Unsupported => {
+            let temp = files::temp_path(&std::env::temp_dir().join("rsconfig-defaults.json"));
+            let saved = defaults.save_json_path(&temp).and_then(|_| fs::read(&temp));
+            let _ = fs::remove_file(&temp);
+
+            data = saved?;
+        }
+        Err(e) => return Err(e),
+    }
+
+    serde_json::from_slice(&data).map_err(io::Error::from)
+}
+
+/// Merges `overlay` into `base`: objects are merged key by key, and any other value replaces the one in `base`.
+/// ### Example
+/// ```rust
+/// use rsconfig::layers;
+/// use serde_json::json;
+///
+/// let mut base = json!({"db": {"host": "localhost", "port": 5432}, "tags": ["a"]});
+/// layers::merge_values(&mut base, json!({"db": {"port": 6432}, "tags": ["b"]}));
+///
+/// assert_eq!(base, json!({"db": {"host": "localhost", "port": 6432}, "tags": ["b"]}));
+/// ```
+pub fn merge_values(base: &mut Value, overlay: Value) {
+    match (base, overlay) {
+        (Value::Object(base), Value::Object(overlay)) => {
+            for (key, value) in overlay {
+                match base.get_mut(&key) {
+                    Some(existing) => merge_values(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, overlay) => *base = overlay,
+    }
+}
+
+fn args_value(args: &[String]) -> Result<Value, LayerError> {
+    let mut root = Map::new();
+
+    for arg in args.iter().take_while(|arg| *arg != "--") {
+        let Some(flag) = arg.strip_prefix("--") else {
+            continue;
+        };
+
+        let error = |message: &str| LayerError::Args {
+            flag: arg.clone(),
+            message: message.to_string(),
+        };
+
+        let (name, value) = match flag.split_once('=') {
+            Some((name, value)) => (name, env::coerce(value).map_err(error)?),
+            None => (flag, Value::Bool(true)),
+        };
+
+        let name = name.replace('-', "_");
+        let segments: Vec<&str> = name.split('.').collect();
+
+        if segments.iter().any(|segment| segment.is_empty()) {
+            return Err(error("name has an empty segment"));
+        }
+
+        // a repeated flag replaces the earlier value
+        env::insert(&mut root, &segments, value).map_err(|message| error(&message))?;
+    }
+
+    Ok(Value::Object(root))
+}
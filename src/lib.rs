@@ -5,21 +5,65 @@
 /// Contains useful functions for importing from files
 pub mod files;
 
+/// Contains the error type returned when loading config files fails
+pub mod error;
+pub use error::ConfigError;
+
 /// Contains some prebuilt configuration structs to quickstart config
 pub mod quick;
 
+/// Contains helpers for loading a config once and sharing it across threads
+pub mod once;
+
+/// Contains helpers for extracting documentation from existing config files
+pub mod docs;
+
+/// Contains checks for external resources referenced by config values, like files, directories and ports
+pub mod requirements;
+
+/// Contains the parsed representation of INI files used by IniConfig
+pub mod ini;
+
+/// Contains the parsed representation of Java-style properties files used by PropertiesConfig
+pub mod properties;
+
+/// Contains helpers for loading configs from prefixed environment variables
+pub mod env;
+
+/// Contains a builder for layering defaults, config files, environment variables and commandline flags
+pub mod layers;
+
+/// Contains helpers for mapping XML documents used by XmlConfig
+#[cfg(feature = "xml")]
+pub mod xml;
+
+/// Contains helpers for encoding the CBOR documents used by CborConfig
+#[cfg(feature = "cbor")]
+pub mod cbor;
+
+/// Contains helpers for scrubbing sensitive values from configs before sharing them
+#[cfg(feature = "anonymize")]
+pub mod anonymize;
+
+/// Contains build information helpers for reporting alongside the config
+#[cfg(feature = "buildinfo")]
+pub mod buildinfo;
+
 use serde_json::Value;
 use yaml_rust::Yaml;
 
-use std::io;
+use std::{
+    collections::HashMap,
+    ffi::OsString,
+    io::{self, Write},
+    path::Path,
+};
 
 /// Represents a configuration struct that can be created from commandline arguments.
 /// ### Example Code
 /// ```rust
 /// use rsconfig::CommandlineConfig;
 ///
-/// use std::env;
-///
 /// // our config class that we can expand upon to add different values
 /// // to expand upon it, simply add more fields and update the import function(s)
 /// #[derive(Debug)]
@@ -36,11 +80,8 @@ use std::io;
 ///
 ///
 /// fn main() {
-///     // fetch commandline args
-///     let args: Vec<String> = env::args().collect();
-///
-///     // load config from commandline args
-///     let mut config = TestConfig::from_env_args(args);
+///     // load config from the commandline args, without the program name
+///     let mut config = TestConfig::from_current_args();
 ///
 ///     // should output TestConfig { test: true } if --test is in the command
 ///     // otherwise, it will print TestConfig { test: false }
@@ -61,6 +102,50 @@ pub trait CommandlineConfig {
     /// # }
     /// ```
     fn from_env_args(args: Vec<String>) -> Self;
+
+    /// Initialize a CommandlineConfig struct from borrowed commandline arguments.
+    /// By default this copies the arguments and calls `from_env_args`;
+    /// implementors can override it to avoid allocating a String per argument.
+    /// ### Example
+    /// ```rust
+    /// # use rsconfig::CommandlineConfig;
+    /// # struct T { test: bool }
+    /// # impl CommandlineConfig for T {
+    /// # fn from_env_args(args: Vec<String>) -> Self { Self::from_env_args_ref(&args.iter().map(String::as_str).collect::<Vec<_>>()) }
+    /// fn from_env_args_ref(args: &[&str]) -> Self {
+    ///     // check if commandline args contains --test
+    ///     Self { test: args.contains(&"--test") }
+    /// }
+    /// # }
+    /// ```
+    fn from_env_args_ref(args: &[&str]) -> Self
+    where
+        Self: Sized,
+    {
+        Self::from_env_args(args.iter().map(|arg| arg.to_string()).collect())
+    }
+
+    /// Initialize a CommandlineConfig struct from the arguments the program was run with, leaving out the program name.
+    /// Arguments that aren't valid UTF-8 are converted like `from_args_os` instead of panicking like `env::args`.
+    fn from_current_args() -> Self
+    where
+        Self: Sized,
+    {
+        Self::from_args_os(std::env::args_os().skip(1).collect())
+    }
+
+    /// Initialize a CommandlineConfig struct from arguments that may not be valid UTF-8, like those from `env::args_os`.
+    /// Invalid sequences are replaced with `U+FFFD` before calling `from_env_args`.
+    fn from_args_os(args: Vec<OsString>) -> Self
+    where
+        Self: Sized,
+    {
+        Self::from_env_args(
+            args.iter()
+                .map(|arg| arg.to_string_lossy().into_owned())
+                .collect(),
+        )
+    }
 }
 
 /// Represents a configuration struct that can be created from a YAML (YML) file.
@@ -68,7 +153,7 @@ pub trait CommandlineConfig {
 /// ```rust
 /// use yaml_rust;
 /// use rsconfig::YamlConfig;
-/// 
+///
 /// use std::{fs, io::Result};
 ///
 /// struct TestConfig {
@@ -105,7 +190,7 @@ pub trait YamlConfig {
     /// # use yaml_rust;
     /// # use rsconfig::YamlConfig;
     /// # use std::io::Result;
-    /// 
+    ///
     /// # struct T { test: bool }
     /// # impl YamlConfig for T {
     /// fn from_yaml(yaml: Vec<yaml_rust::Yaml>) -> Self {
@@ -123,8 +208,8 @@ pub trait YamlConfig {
     /// ```rust
     /// # use std::{fs, io::Result};
     /// # use rsconfig::YamlConfig;
-    /// # use rust_yaml::Yaml;
-    /// 
+    /// # use yaml_rust::Yaml;
+    ///
     /// # struct T { test: bool }
     /// # impl YamlConfig for T {
     /// # fn from_yaml(yaml: Vec<Yaml>) -> Self {Self{test: false}}
@@ -146,16 +231,61 @@ pub trait YamlConfig {
     /// # }
     /// ```
     fn save_yaml(&self, path: &str) -> io::Result<()>;
+
+    /// Write a YamlConfig struct's contents as YAML to a writer, such as an archive entry or an HTTP response body.
+    /// Returns an `Unsupported` error by default, meaning the type can only be saved to a file.
+    /// Implementing it lets serialization be tested without touching the disk, and `save_yaml` can forward to it.
+    /// ### Example
+    /// ```rust
+    /// # use std::{fs::File, io::{Result, Write}};
+    /// # use rsconfig::YamlConfig;
+    /// # use yaml_rust::Yaml;
+    ///
+    /// # struct T { test: bool }
+    /// # impl YamlConfig for T {
+    /// # fn from_yaml(yaml: Vec<Yaml>) -> Self {Self{test: false}}
+    /// fn write_yaml<W: Write>(&self, mut writer: W) -> Result<()> {
+    ///     writeln!(writer, "test: {}", self.test)
+    /// }
+    ///
+    /// fn save_yaml(&self, path: &str) -> Result<()> {
+    ///     self.write_yaml(File::create(path)?)
+    /// }
+    /// # }
+    /// let mut data = Vec::new();
+    /// T { test: true }.write_yaml(&mut data)?;
+    /// assert_eq!(data, b"test: true\n");
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    fn write_yaml<W: Write>(&self, writer: W) -> io::Result<()>
+    where
+        Self: Sized,
+    {
+        let _ = writer;
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "this config type can't write YAML to a writer, only save it to a file",
+        ))
+    }
+
+    /// Same as `save_yaml`, for paths that aren't a `&str`, like a `PathBuf`.
+    /// Fails with `InvalidInput` if the path isn't valid UTF-8.
+    fn save_yaml_path(&self, path: impl AsRef<Path>) -> io::Result<()>
+    where
+        Self: Sized,
+    {
+        self.save_yaml(path_str(path.as_ref())?)
+    }
 }
 
 /// Represents a configuration struct that can be created from a JSON file.
 /// ### Example
 /// ```rust
-/// use serde_json;
+/// use serde_json::{self, Value};
 ///
 /// use rsconfig::JsonConfig;
-/// 
-/// use std::fs;
+///
+/// use std::{collections::HashMap, fs, io};
 ///
 /// #[derive(Debug)]
 /// struct TestConfig {
@@ -171,9 +301,9 @@ pub trait YamlConfig {
 ///
 ///     fn save_json(&self, path: &str) -> io::Result<()> {
 ///         // convert to json pretty format and save
-///         let mut m: Hashmap<&str, Value> = Hashmap::new();
-///         m.insert("test", &Value::from(self.test));
-///         let data = serde_json::to_string_pretty(m).unwrap();
+///         let mut m: HashMap<&str, Value> = HashMap::new();
+///         m.insert("test", Value::from(self.test));
+///         let data = serde_json::to_string_pretty(&m).unwrap();
 ///         fs::write(path, data).unwrap();
 ///
 ///         Ok(())
@@ -187,7 +317,7 @@ pub trait JsonConfig {
     /// # use serde_json;
     /// # use rsconfig::JsonConfig;
     /// # use std::io::Result;
-    /// 
+    ///
     /// # struct T { test: bool }
     /// # impl JsonConfig for T {
     /// fn from_json(val: serde_json::Value) -> Self {
@@ -206,7 +336,7 @@ pub trait JsonConfig {
     /// # use std::{fs, io::Result, collections::HashMap};
     /// # use serde_json::Value;
     /// # use rsconfig::JsonConfig;
-    /// 
+    ///
     /// # struct T { test: bool }
     /// # impl JsonConfig for T {
     /// # fn from_json(val: Value) -> Self{Self{test: true}}
@@ -222,95 +352,802 @@ pub trait JsonConfig {
     /// # }
     /// ```
     fn save_json(&self, path: &str) -> io::Result<()>;
+
+    /// Write a JsonConfig struct's contents as JSON to a writer, such as an archive entry or an HTTP response body.
+    /// Returns an `Unsupported` error by default, meaning the type can only be saved to a file.
+    /// Implementing it lets serialization be tested without touching the disk, and `save_json` can forward to it.
+    /// ### Example
+    /// ```rust
+    /// # use std::{fs::File, io::{Result, Write}};
+    /// # use serde_json::{json, Value};
+    /// # use rsconfig::JsonConfig;
+    ///
+    /// # struct T { test: bool }
+    /// # impl JsonConfig for T {
+    /// # fn from_json(val: Value) -> Self{Self{test: true}}
+    /// fn write_json<W: Write>(&self, writer: W) -> Result<()> {
+    ///     serde_json::to_writer_pretty(writer, &json!({ "test": self.test }))?;
+    ///     Ok(())
+    /// }
+    ///
+    /// fn save_json(&self, path: &str) -> Result<()> {
+    ///     self.write_json(File::create(path)?)
+    /// }
+    /// # }
+    /// let mut data = Vec::new();
+    /// T { test: true }.write_json(&mut data)?;
+    /// assert_eq!(serde_json::from_slice::<Value>(&data)?["test"], true);
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    fn write_json<W: Write>(&self, writer: W) -> io::Result<()>
+    where
+        Self: Sized,
+    {
+        let _ = writer;
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "this config type can't write JSON to a writer, only save it to a file",
+        ))
+    }
+
+    /// Same as `save_json`, for paths that aren't a `&str`, like a `PathBuf`.
+    /// Fails with `InvalidInput` if the path isn't valid UTF-8.
+    fn save_json_path(&self, path: impl AsRef<Path>) -> io::Result<()>
+    where
+        Self: Sized,
+    {
+        self.save_json(path_str(path.as_ref())?)
+    }
 }
 
-/// Represents a configuration struct that can be created from a number of file types.
+// the save methods take a &str, so paths from elsewhere have to be valid UTF-8
+fn path_str(path: &Path) -> io::Result<&str> {
+    path.to_str().ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{} is not valid UTF-8", path.display()),
+        )
+    })
+}
+
+/// Represents a configuration struct that can be created from a TOML file.
 /// ### Example
 /// ```rust
-/// use rsconfig::{YamlConfig, JsonConfig, FileConfig};
+/// use rsconfig::TomlConfig;
+///
+/// use std::{fs, io};
 ///
-/// use serde_json;
-/// use yaml_rust;
-/// 
-/// // rsconfig-macros crate has a derive macro for this trait
 /// #[derive(Debug)]
 /// struct TestConfig {
 ///     test: bool
 /// }
 ///
-/// impl YamlConfig for TestConfig {
-///     fn from_yaml(yaml: Vec<yaml_rust::Yaml>) -> Self {
-///         Self { test: *&yaml[0]["test"].as_bool().unwrap() }
+/// impl TomlConfig for TestConfig {
+///     fn from_toml(val: toml::Value) -> Self {
+///         // look for "test" val
+///         // NOTE: this code is not error-safe, will panic if the toml does not contain a bool named "test"
+///         Self { test: val["test"].as_bool().unwrap() }
 ///     }
 ///
-///     fn save_yaml(&self, path: &str) -> Result<()> {
-///         let mut data = "test: ".to_string();
-///         data.push_str(self.test.to_string().as_str());
+///     fn save_toml(&self, path: &str) -> io::Result<()> {
+///         // build a table and save it
+///         let mut table = toml::Table::new();
+///         table.insert("test".to_string(), toml::Value::from(self.test));
+///         fs::write(path, table.to_string())
+///     }
+/// }
+/// ```
+pub trait TomlConfig {
+    /// Initialize a TomlConfig struct from a parsed TOML document.
+    /// ### Example
+    /// ```rust
+    /// # use rsconfig::TomlConfig;
+    /// # use std::io::Result;
+    ///
+    /// # struct T { test: bool }
+    /// # impl TomlConfig for T {
+    /// fn from_toml(val: toml::Value) -> Self {
+    ///     // look for "test" val
+    ///     // NOTE: this code is not error-safe, will panic if the toml does not contain a bool named "test"
+    ///     Self { test: val["test"].as_bool().unwrap() }
+    /// }
+    /// # fn save_toml(&self, path: &str) -> Result<()> {Ok(())}
+    /// # }
+    /// ```
+    fn from_toml(val: toml::Value) -> Self;
+
+    /// Save a TomlConfig struct's contents to a TOML file.
+    /// ### Example
+    /// ```rust
+    /// # use rsconfig::TomlConfig;
+    /// # use std::{fs, io::Result};
+    ///
+    /// # struct T { test: bool }
+    /// # impl TomlConfig for T {
+    /// # fn from_toml(val: toml::Value) -> Self {Self{test: true}}
+    /// fn save_toml(&self, path: &str) -> Result<()> {
+    ///     // build a table and save it
+    ///     let mut table = toml::Table::new();
+    ///     table.insert("test".to_string(), toml::Value::from(self.test));
+    ///     fs::write(path, table.to_string())
+    /// }
+    /// # }
+    /// ```
+    fn save_toml(&self, path: &str) -> io::Result<()>;
+}
+
+/// Represents a configuration struct that can be created from an INI file.
+/// ### Example
+/// ```rust
+/// use rsconfig::{ini::Ini, IniConfig};
 ///
-///         fs::write(path, data).unwrap();
+/// use std::{fs, io};
 ///
-///         Ok(())
-///     }
+/// #[derive(Debug)]
+/// struct TestConfig {
+///     test: bool
 /// }
 ///
-/// impl JsonConfig for TestConfig {
-///     fn from_json(val: Value) -> Self {
-///         Self { test: val["test"].as_bool().unwrap() }
+/// impl IniConfig for TestConfig {
+///     fn from_ini(ini: Ini) -> Self {
+///         // look for "test" in the global section
+///         // NOTE: this code is not error-safe, will panic if the file does not contain a bool named "test"
+///         Self { test: ini.get("", "test").unwrap().parse().unwrap() }
 ///     }
 ///
-///     fn save_json(&self, path: &str) -> io::Result<()> {
-///         // convert to json pretty format and save
-///         let mut m: Hashmap<&str, Value> = Hashmap::new();
-///         m.insert("test", &Value::from(self.test));
-///         let data = serde_json::to_string_pretty(m).unwrap();
-///         fs::write(path, data).unwrap();
-///
-///         Ok(())
+///     fn save_ini(&self, path: &str) -> io::Result<()> {
+///         let mut ini = Ini::new();
+///         ini.set("", "test", self.test.to_string());
+///         fs::write(path, ini.to_string())
 ///     }
 /// }
-/// impl FileConfig for TestConfig {}
 /// ```
+pub trait IniConfig {
+    /// Initialize an IniConfig struct from a parsed INI file.
+    /// ### Example
+    /// ```rust
+    /// # use rsconfig::{ini::Ini, IniConfig};
+    /// # use std::io::Result;
+    ///
+    /// # struct T { port: u16 }
+    /// # impl IniConfig for T {
+    /// fn from_ini(ini: Ini) -> Self {
+    ///     // read "port" from the [server] section
+    ///     // NOTE: this code is not error-safe, will panic if the section or key is missing
+    ///     Self { port: ini.get("server", "port").unwrap().parse().unwrap() }
+    /// }
+    /// # fn save_ini(&self, path: &str) -> Result<()> {Ok(())}
+    /// # }
+    /// ```
+    fn from_ini(ini: ini::Ini) -> Self;
 
-pub trait FileConfig: YamlConfig + JsonConfig {}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    use std::{collections::HashMap, env, fs, io::Result};
+    /// Save an IniConfig struct's contents to an INI file.
+    /// ### Example
+    /// ```rust
+    /// # use rsconfig::{ini::Ini, IniConfig};
+    /// # use std::{fs, io::Result};
+    ///
+    /// # struct T { port: u16 }
+    /// # impl IniConfig for T {
+    /// # fn from_ini(ini: Ini) -> Self {Self{port: 0}}
+    /// fn save_ini(&self, path: &str) -> Result<()> {
+    ///     let mut ini = Ini::new();
+    ///     ini.set("server", "port", self.port.to_string());
+    ///     fs::write(path, ini.to_string())
+    /// }
+    /// # }
+    /// ```
+    fn save_ini(&self, path: &str) -> io::Result<()>;
+}
 
-    // config class that we can expand upon to add different values
-    #[derive(Debug)]
-    struct TestConfig {
-        test: bool,
-    }
+/// Represents a configuration struct that can be created from a Java-style `.properties` file.
+/// ### Example
+/// ```rust
+/// use rsconfig::{properties::Properties, PropertiesConfig};
+///
+/// use std::{fs, io};
+///
+/// #[derive(Debug)]
+/// struct TestConfig {
+///     test: bool
+/// }
+///
+/// impl PropertiesConfig for TestConfig {
+///     fn from_properties(props: Properties) -> Self {
+///         // NOTE: this code is not error-safe, will panic if the file does not contain a bool named "test"
+///         Self { test: props.get("test").unwrap().parse().unwrap() }
+///     }
+///
+///     fn save_properties(&self, path: &str) -> io::Result<()> {
+///         let mut props = Properties::new();
+///         props.set("test", self.test.to_string());
+///         fs::write(path, props.to_string())
+///     }
+/// }
+/// ```
+pub trait PropertiesConfig {
+    /// Initialize a PropertiesConfig struct from a parsed properties file.
+    /// ### Example
+    /// ```rust
+    /// # use rsconfig::{properties::Properties, PropertiesConfig};
+    /// # use std::io::Result;
+    ///
+    /// # struct T { port: u16 }
+    /// # impl PropertiesConfig for T {
+    /// fn from_properties(props: Properties) -> Self {
+    ///     // NOTE: this code is not error-safe, will panic if the key is missing
+    ///     Self { port: props.get("server.port").unwrap().parse().unwrap() }
+    /// }
+    /// # fn save_properties(&self, path: &str) -> Result<()> {Ok(())}
+    /// # }
+    /// ```
+    fn from_properties(props: properties::Properties) -> Self;
 
-    impl CommandlineConfig for TestConfig {
-        fn from_env_args(args: Vec<String>) -> Self {
-            Self {
-                test: args.contains(&"test".to_string()),
-            }
-        }
-    }
+    /// Save a PropertiesConfig struct's contents to a properties file.
+    /// ### Example
+    /// ```rust
+    /// # use rsconfig::{properties::Properties, PropertiesConfig};
+    /// # use std::{fs, io::Result};
+    ///
+    /// # struct T { port: u16 }
+    /// # impl PropertiesConfig for T {
+    /// # fn from_properties(props: Properties) -> Self {Self{port: 0}}
+    /// fn save_properties(&self, path: &str) -> Result<()> {
+    ///     let mut props = Properties::new();
+    ///     props.set("server.port", self.port.to_string());
+    ///     fs::write(path, props.to_string())
+    /// }
+    /// # }
+    /// ```
+    fn save_properties(&self, path: &str) -> io::Result<()>;
+}
 
-    impl YamlConfig for TestConfig {
-        fn from_yaml(yaml: Vec<yaml_rust::Yaml>) -> Self {
-            Self {
-                test: *&yaml[0]["test"].as_bool().unwrap(),
-            }
-        }
+/// Represents a configuration struct that can be created from an XML file.
+/// The root element is passed as an `xmltree::Element`; `xml::to_json` maps it to JSON if that's easier to work with.
+/// ### Example
+/// ```rust
+/// use rsconfig::XmlConfig;
+/// use xmltree::Element;
+///
+/// use std::{fs::File, io};
+///
+/// #[derive(Debug)]
+/// struct TestConfig {
+///     test: bool
+/// }
+///
+/// impl XmlConfig for TestConfig {
+///     fn from_xml(root: Element) -> Self {
+///         // read the text of the <test> child element
+///         // NOTE: this code is not error-safe, will panic if the file does not contain a <test> bool
+///         Self { test: root.get_child("test").unwrap().get_text().unwrap().parse().unwrap() }
+///     }
+///
+///     fn save_xml(&self, path: &str) -> io::Result<()> {
+///         let mut test = Element::new("test");
+///         test.children.push(xmltree::XMLNode::Text(self.test.to_string()));
+///
+///         let mut root = Element::new("config");
+///         root.children.push(xmltree::XMLNode::Element(test));
+///
+///         root.write(File::create(path)?).map_err(io::Error::other)
+///     }
+/// }
+/// ```
+#[cfg(feature = "xml")]
+pub trait XmlConfig {
+    /// Initialize an XmlConfig struct from the root element of a parsed XML file.
+    /// ### Example
+    /// ```rust
+    /// # use rsconfig::XmlConfig;
+    /// # use std::io::Result;
+    ///
+    /// # struct T { port: u16 }
+    /// # impl XmlConfig for T {
+    /// fn from_xml(root: xmltree::Element) -> Self {
+    ///     // read the "port" attribute of the root element
+    ///     // NOTE: this code is not error-safe, will panic if the attribute is missing
+    ///     Self { port: root.attributes["port"].parse().unwrap() }
+    /// }
+    /// # fn save_xml(&self, path: &str) -> Result<()> {Ok(())}
+    /// # }
+    /// ```
+    fn from_xml(root: xmltree::Element) -> Self;
 
-        fn save_yaml(&self, path: &str) -> Result<()> {
-            let mut data = "test: ".to_string();
-            data.push_str(self.test.to_string().as_str());
+    /// Save an XmlConfig struct's contents to an XML file.
+    /// ### Example
+    /// ```rust
+    /// # use rsconfig::XmlConfig;
+    /// # use std::{fs::File, io::{self, Result}};
+    ///
+    /// # struct T { port: u16 }
+    /// # impl XmlConfig for T {
+    /// # fn from_xml(root: xmltree::Element) -> Self {Self{port: 0}}
+    /// fn save_xml(&self, path: &str) -> Result<()> {
+    ///     let mut root = xmltree::Element::new("config");
+    ///     root.attributes.insert("port".to_string(), self.port.to_string());
+    ///
+    ///     root.write(File::create(path)?).map_err(io::Error::other)
+    /// }
+    /// # }
+    /// ```
+    fn save_xml(&self, path: &str) -> io::Result<()>;
+}
 
-            fs::write(path, data).unwrap();
+/// Represents a configuration struct that can be created from MessagePack data, such as config blobs received over the network.
+/// ### Example
+/// ```rust
+/// use rsconfig::MsgpackConfig;
+///
+/// use std::{fs, io};
+///
+/// #[derive(Debug)]
+/// struct TestConfig {
+///     test: bool
+/// }
+///
+/// impl MsgpackConfig for TestConfig {
+///     fn from_msgpack(val: rmpv::Value) -> Self {
+///         // NOTE: this code is not error-safe, will panic if the data does not contain a bool named "test"
+///         Self { test: val["test"].as_bool().unwrap() }
+///     }
+///
+///     fn save_msgpack(&self, path: &str) -> io::Result<()> {
+///         let val = rmpv::Value::Map(vec![("test".into(), self.test.into())]);
+///
+///         let mut data = Vec::new();
+///         rmpv::encode::write_value(&mut data, &val)?;
+///         fs::write(path, data)
+///     }
+/// }
+/// ```
+#[cfg(feature = "rmp")]
+pub trait MsgpackConfig {
+    /// Initialize a MsgpackConfig struct from a decoded MessagePack value.
+    /// ### Example
+    /// ```rust
+    /// # use rsconfig::MsgpackConfig;
+    /// # use std::io::Result;
+    ///
+    /// # struct T { port: u64 }
+    /// # impl MsgpackConfig for T {
+    /// fn from_msgpack(val: rmpv::Value) -> Self {
+    ///     // NOTE: this code is not error-safe, will panic if the value is missing
+    ///     Self { port: val["server"]["port"].as_u64().unwrap() }
+    /// }
+    /// # fn save_msgpack(&self, path: &str) -> Result<()> {Ok(())}
+    /// # }
+    /// ```
+    fn from_msgpack(val: rmpv::Value) -> Self;
 
-            Ok(())
-        }
-    }
+    /// Save a MsgpackConfig struct's contents to a MessagePack file.
+    /// ### Example
+    /// ```rust
+    /// # use rsconfig::MsgpackConfig;
+    /// # use std::{fs, io::Result};
+    ///
+    /// # struct T { port: u64 }
+    /// # impl MsgpackConfig for T {
+    /// # fn from_msgpack(val: rmpv::Value) -> Self {Self{port: 0}}
+    /// fn save_msgpack(&self, path: &str) -> Result<()> {
+    ///     let server = rmpv::Value::Map(vec![("port".into(), self.port.into())]);
+    ///     let val = rmpv::Value::Map(vec![("server".into(), server)]);
+    ///
+    ///     let mut data = Vec::new();
+    ///     rmpv::encode::write_value(&mut data, &val)?;
+    ///     fs::write(path, data)
+    /// }
+    /// # }
+    /// ```
+    fn save_msgpack(&self, path: &str) -> io::Result<()>;
+}
 
-    impl JsonConfig for TestConfig {
+/// Represents a configuration struct that can be created from a CBOR file.
+/// Use `cbor::to_canonical_vec` when saving, so the same config always produces the same bytes.
+/// ### Example
+/// ```rust
+/// use rsconfig::{cbor, CborConfig};
+/// use ciborium::Value;
+///
+/// use std::{fs, io};
+///
+/// #[derive(Debug)]
+/// struct TestConfig {
+///     test: bool
+/// }
+///
+/// impl CborConfig for TestConfig {
+///     fn from_cbor(val: Value) -> Self {
+///         // look for "test" in the top-level map
+///         // NOTE: this code is not error-safe, will panic if the file does not contain a bool named "test"
+///         let map = val.into_map().unwrap();
+///         let (_, test) = map.into_iter().find(|(k, _)| k.as_text() == Some("test")).unwrap();
+///
+///         Self { test: test.as_bool().unwrap() }
+///     }
+///
+///     fn save_cbor(&self, path: &str) -> io::Result<()> {
+///         let val = Value::Map(vec![("test".into(), self.test.into())]);
+///         fs::write(path, cbor::to_canonical_vec(&val)?)
+///     }
+/// }
+/// ```
+#[cfg(feature = "cbor")]
+pub trait CborConfig {
+    /// Initialize a CborConfig struct from a decoded CBOR value.
+    /// Indefinite-length maps, arrays and strings have already been joined when this is called.
+    fn from_cbor(val: ciborium::Value) -> Self;
+
+    /// Save a CborConfig struct's contents to a CBOR file.
+    fn save_cbor(&self, path: &str) -> io::Result<()>;
+}
+
+/// Represents a configuration struct stored in a compact binary file, encoded with bincode.
+/// Meant for large machine-generated configs that are never edited by hand, where parsing text at startup is too slow.
+/// Everything is provided through serde, so implementing it only takes an empty impl block.
+/// Corrupt or truncated data is returned as an `InvalidData` error instead of panicking.
+/// ### Example
+/// ```rust
+/// use rsconfig::BinaryConfig;
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Debug, PartialEq, Serialize, Deserialize)]
+/// struct TestConfig {
+///     test: bool
+/// }
+///
+/// impl BinaryConfig for TestConfig {}
+///
+/// let bytes = TestConfig { test: true }.to_bytes().unwrap();
+/// assert_eq!(TestConfig::from_bytes(&bytes).unwrap(), TestConfig { test: true });
+/// ```
+#[cfg(feature = "bincode")]
+pub trait BinaryConfig: serde::Serialize + serde::de::DeserializeOwned {
+    /// Initialize a BinaryConfig struct from bincode-encoded bytes.
+    /// The bytes must contain exactly one encoded value, trailing bytes are an error.
+    fn from_bytes(bytes: &[u8]) -> io::Result<Self> {
+        let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidData, message);
+
+        let (config, read) = bincode::serde::decode_from_slice(bytes, bincode::config::standard())
+            .map_err(|e| invalid(format!("Failed to decode binary config: {}", e)))?;
+
+        if read != bytes.len() {
+            return Err(invalid(format!(
+                "Failed to decode binary config: {} unexpected trailing bytes",
+                bytes.len() - read
+            )));
+        }
+
+        Ok(config)
+    }
+
+    /// Encode a BinaryConfig struct's contents with bincode.
+    fn to_bytes(&self) -> io::Result<Vec<u8>> {
+        bincode::serde::encode_to_vec(self, bincode::config::standard())
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))
+    }
+
+    /// Save a BinaryConfig struct's contents to a binary file.
+    fn save_binary(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        std::fs::write(path, self.to_bytes()?)
+    }
+}
+
+/// Represents a configuration struct that can be created from a dotenv (.env) file.
+/// ### Example
+/// ```rust
+/// use rsconfig::EnvFileConfig;
+///
+/// use std::collections::HashMap;
+///
+/// #[derive(Debug)]
+/// struct TestConfig {
+///     database_url: String
+/// }
+///
+/// impl EnvFileConfig for TestConfig {
+///     fn from_env_map(vars: HashMap<String, String>) -> Self {
+///         // NOTE: this code is not error-safe, will panic if DATABASE_URL is missing
+///         Self { database_url: vars["DATABASE_URL"].clone() }
+///     }
+/// }
+/// ```
+pub trait EnvFileConfig {
+    /// Initialize an EnvFileConfig struct from the variables defined in a dotenv file.
+    fn from_env_map(vars: HashMap<String, String>) -> Self;
+}
+
+/// Represents a configuration struct that can be created from a number of file types.
+/// ### Example
+/// ```rust
+/// use rsconfig::{YamlConfig, JsonConfig, FileConfig};
+///
+/// use serde_json::{self, Value};
+/// use yaml_rust;
+///
+/// use std::{collections::HashMap, fs, io::{self, Result}};
+///
+/// // rsconfig-macros crate has a derive macro for this trait
+/// #[derive(Debug)]
+/// struct TestConfig {
+///     test: bool
+/// }
+///
+/// impl YamlConfig for TestConfig {
+///     fn from_yaml(yaml: Vec<yaml_rust::Yaml>) -> Self {
+///         Self { test: *&yaml[0]["test"].as_bool().unwrap() }
+///     }
+///
+///     fn save_yaml(&self, path: &str) -> Result<()> {
+///         let mut data = "test: ".to_string();
+///         data.push_str(self.test.to_string().as_str());
+///
+///         fs::write(path, data).unwrap();
+///
+///         Ok(())
+///     }
+/// }
+///
+/// impl JsonConfig for TestConfig {
+///     fn from_json(val: Value) -> Self {
+///         Self { test: val["test"].as_bool().unwrap() }
+///     }
+///
+///     fn save_json(&self, path: &str) -> io::Result<()> {
+///         // convert to json pretty format and save
+///         let mut m: HashMap<&str, Value> = HashMap::new();
+///         m.insert("test", Value::from(self.test));
+///         let data = serde_json::to_string_pretty(&m).unwrap();
+///         fs::write(path, data).unwrap();
+///
+///         Ok(())
+///     }
+/// }
+/// impl FileConfig for TestConfig {}
+/// ```
+///
+/// Formats beyond YAML and JSON are opt-in, so existing implementations keep compiling.
+/// To let `files::load_from_file` load `.toml` files, implement TomlConfig and forward to it
+/// (the same goes for `try_from_ini`/IniConfig, `try_from_properties`/PropertiesConfig, `try_from_env_map`/EnvFileConfig, `try_from_xml`/XmlConfig, `try_from_msgpack`/MsgpackConfig, `try_from_cbor`/CborConfig and `try_from_binary`/BinaryConfig):
+/// ```rust
+/// # use rsconfig::{YamlConfig, JsonConfig, TomlConfig, FileConfig};
+/// # use std::io::Result;
+/// # struct TestConfig { test: bool }
+/// # impl YamlConfig for TestConfig {
+/// #     fn from_yaml(yaml: Vec<yaml_rust::Yaml>) -> Self { Self { test: yaml[0]["test"].as_bool().unwrap() } }
+/// #     fn save_yaml(&self, path: &str) -> Result<()> { Ok(()) }
+/// # }
+/// # impl JsonConfig for TestConfig {
+/// #     fn from_json(val: serde_json::Value) -> Self { Self { test: val["test"].as_bool().unwrap() } }
+/// #     fn save_json(&self, path: &str) -> Result<()> { Ok(()) }
+/// # }
+/// # impl TomlConfig for TestConfig {
+/// #     fn from_toml(val: toml::Value) -> Self { Self { test: val["test"].as_bool().unwrap() } }
+/// #     fn save_toml(&self, path: &str) -> Result<()> { Ok(()) }
+/// # }
+/// impl FileConfig for TestConfig {
+///     fn try_from_toml(val: toml::Value) -> Option<Self> {
+///         Some(Self::from_toml(val))
+///     }
+/// }
+/// ```
+pub trait FileConfig: YamlConfig + JsonConfig {
+    /// Initialize a FileConfig struct from a TOML document loaded by `files::load_from_file`.
+    /// Returns None by default, meaning the type doesn't support TOML.
+    fn try_from_toml(val: toml::Value) -> Option<Self>
+    where
+        Self: Sized,
+    {
+        let _ = val;
+        None
+    }
+
+    /// Initialize a FileConfig struct from an INI file loaded by `files::load_from_file`.
+    /// Returns None by default, meaning the type doesn't support INI.
+    fn try_from_ini(ini: ini::Ini) -> Option<Self>
+    where
+        Self: Sized,
+    {
+        let _ = ini;
+        None
+    }
+
+    /// Initialize a FileConfig struct from a properties file loaded by `files::load_from_file`.
+    /// Returns None by default, meaning the type doesn't support properties files.
+    fn try_from_properties(props: properties::Properties) -> Option<Self>
+    where
+        Self: Sized,
+    {
+        let _ = props;
+        None
+    }
+
+    /// Initialize a FileConfig struct from a dotenv (.env) file loaded by `files::load_from_file`.
+    /// Returns None by default, meaning the type doesn't support dotenv files.
+    fn try_from_env_map(vars: HashMap<String, String>) -> Option<Self>
+    where
+        Self: Sized,
+    {
+        let _ = vars;
+        None
+    }
+
+    /// Initialize a FileConfig struct from an XML file loaded by `files::load_from_file`.
+    /// Returns None by default, meaning the type doesn't support XML.
+    #[cfg(feature = "xml")]
+    fn try_from_xml(root: xmltree::Element) -> Option<Self>
+    where
+        Self: Sized,
+    {
+        let _ = root;
+        None
+    }
+
+    /// Initialize a FileConfig struct from a MessagePack file loaded by `files::load_from_file`.
+    /// Returns None by default, meaning the type doesn't support MessagePack.
+    #[cfg(feature = "rmp")]
+    fn try_from_msgpack(val: rmpv::Value) -> Option<Self>
+    where
+        Self: Sized,
+    {
+        let _ = val;
+        None
+    }
+
+    /// Initialize a FileConfig struct from a CBOR file loaded by `files::load_from_file`.
+    /// Returns None by default, meaning the type doesn't support CBOR.
+    #[cfg(feature = "cbor")]
+    fn try_from_cbor(val: ciborium::Value) -> Option<Self>
+    where
+        Self: Sized,
+    {
+        let _ = val;
+        None
+    }
+
+    /// Initialize a FileConfig struct from a binary (.bin) file loaded by `files::load_from_file`.
+    /// Returns None by default, meaning the type doesn't support binary files.
+    /// Return `Some(Err(..))` for bytes that can't be decoded, like corrupt or truncated files.
+    #[cfg(feature = "bincode")]
+    fn try_from_binary(
+        bytes: Vec<u8>,
+    ) -> Option<Result<Self, Box<dyn std::error::Error + Send + Sync>>>
+    where
+        Self: Sized,
+    {
+        let _ = bytes;
+        None
+    }
+}
+
+/// Represents a config overlay that only holds the values a source sets, usually a copy of the complete config
+/// with every field wrapped in `Option`. Load it with `files::load_partial_from_yaml` (or the JSON and file versions)
+/// and apply it onto the complete config, so a file can override only `log_level` and leave everything else alone.
+/// ### Example
+/// ```rust
+/// use rsconfig::PartialConfig;
+///
+/// struct TestConfig {
+///     log_level: String,
+///     verbose: bool,
+/// }
+///
+/// struct PartialTestConfig {
+///     log_level: Option<String>,
+///     verbose: Option<bool>,
+/// }
+///
+/// impl PartialConfig for PartialTestConfig {
+///     type Complete = TestConfig;
+///
+///     fn apply_to(self, target: &mut TestConfig) {
+///         if let Some(log_level) = self.log_level {
+///             target.log_level = log_level;
+///         }
+///         if let Some(verbose) = self.verbose {
+///             target.verbose = verbose;
+///         }
+///     }
+/// }
+///
+/// let mut config = TestConfig { log_level: "info".to_string(), verbose: false };
+/// PartialTestConfig { log_level: Some("debug".to_string()), verbose: None }.apply_to(&mut config);
+///
+/// assert_eq!(config.log_level, "debug");
+/// assert!(!config.verbose);
+/// ```
+pub trait PartialConfig {
+    /// The config type this overlay applies to
+    type Complete;
+
+    /// Overwrites the fields of `target` that this overlay sets, leaving the others as they are.
+    fn apply_to(self, target: &mut Self::Complete);
+}
+
+/// Represents a configuration struct that can have another instance overlaid onto it, used by `files::load_merged`.
+/// Merges are deep: scalar fields (and lists) take the value from `other`, while nested maps and config structs
+/// are merged key by key, so a user config only needs the fields it changes.
+/// ### Example
+/// ```rust
+/// use rsconfig::MergeConfig;
+///
+/// use std::collections::HashMap;
+///
+/// struct TestConfig {
+///     // None means the file didn't set it
+///     test: Option<bool>,
+///     aliases: HashMap<String, String>,
+/// }
+///
+/// impl MergeConfig for TestConfig {
+///     fn merge(&mut self, other: Self) {
+///         if other.test.is_some() {
+///             self.test = other.test;
+///         }
+///         self.aliases.extend(other.aliases);
+///     }
+/// }
+/// ```
+pub trait MergeConfig {
+    /// Overlays `other` onto this config, with the values set in `other` winning.
+    fn merge(&mut self, other: Self);
+}
+
+// objects are merged key by key, anything else is replaced
+impl MergeConfig for serde_json::Value {
+    fn merge(&mut self, other: Self) {
+        layers::merge_values(self, other);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::{
+        collections::HashMap,
+        env, fs,
+        io::Result,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc, Barrier,
+        },
+        thread,
+        time::Duration,
+    };
+
+    // config class that we can expand upon to add different values
+    #[derive(Debug, Default)]
+    #[cfg_attr(feature = "bincode", derive(serde::Serialize, serde::Deserialize))]
+    struct TestConfig {
+        test: bool,
+    }
+
+    impl CommandlineConfig for TestConfig {
+        fn from_env_args(args: Vec<String>) -> Self {
+            Self {
+                test: args.contains(&"test".to_string()),
+            }
+        }
+    }
+
+    impl YamlConfig for TestConfig {
+        fn from_yaml(yaml: Vec<yaml_rust::Yaml>) -> Self {
+            Self {
+                test: yaml[0]["test"].as_bool().unwrap(),
+            }
+        }
+
+        fn save_yaml(&self, path: &str) -> Result<()> {
+            self.write_yaml(fs::File::create(path)?)
+        }
+
+        fn write_yaml<W: Write>(&self, mut writer: W) -> Result<()> {
+            write!(writer, "test: {}", self.test)
+        }
+    }
+
+    impl JsonConfig for TestConfig {
         fn from_json(val: Value) -> Self {
             Self {
                 test: val["test"].as_bool().unwrap(),
@@ -318,79 +1155,3223 @@ mod tests {
         }
 
         fn save_json(&self, path: &str) -> io::Result<()> {
-            // convert to json pretty format and save
+            self.write_json(fs::File::create(path)?)
+        }
+
+        fn write_json<W: Write>(&self, writer: W) -> Result<()> {
+            // convert to json pretty format and write
             let mut m: HashMap<&str, Value> = HashMap::new();
             m.insert("test", Value::from(self.test));
-            let data = serde_json::to_string_pretty(&m).unwrap();
-            fs::write(path, data).unwrap();
+            serde_json::to_writer_pretty(writer, &m)?;
 
             Ok(())
         }
     }
-    
-    impl FileConfig for TestConfig {}
 
-    // path to test files
-    const YAML_PATH: &str = "testing\\test.yml";
-    const JSON_PATH: &str = "testing\\test.json";
+    impl TomlConfig for TestConfig {
+        fn from_toml(val: toml::Value) -> Self {
+            Self {
+                test: val["test"].as_bool().unwrap(),
+            }
+        }
 
-    #[test]
-    fn args_test() {
-        // under normal test command (cargo test --package rsconfig --lib -- tests --nocapture),
-        // this will always create `config` with `test` as false
+        fn save_toml(&self, path: &str) -> Result<()> {
+            let mut table = toml::Table::new();
+            table.insert("test".to_string(), toml::Value::from(self.test));
+
+            fs::write(path, table.to_string())
+        }
+    }
 
-        let args: Vec<String> = env::args().collect();
+    impl IniConfig for TestConfig {
+        fn from_ini(ini: ini::Ini) -> Self {
+            Self {
+                test: ini.get("", "test").unwrap().parse().unwrap(),
+            }
+        }
 
-        let mut config = TestConfig::from_env_args(args);
+        fn save_ini(&self, path: &str) -> Result<()> {
+            let mut ini = ini::Ini::new();
+            ini.set("", "test", self.test.to_string());
 
-        println!("{:?}", config);
+            fs::write(path, ini.to_string())
+        }
+    }
 
-        change_config(&mut config);
+    impl PropertiesConfig for TestConfig {
+        fn from_properties(props: properties::Properties) -> Self {
+            Self {
+                test: props.get("test").unwrap().parse().unwrap(),
+            }
+        }
+
+        fn save_properties(&self, path: &str) -> Result<()> {
+            let mut props = properties::Properties::new();
+            props.set("test", self.test.to_string());
+
+            fs::write(path, props.to_string())
+        }
     }
 
-    #[test]
-    fn yaml_test() {
-        // loads from yaml; could use files::load_from_file(),
-        // but since we already know the filetype, it's better to just do this
+    impl FileConfig for TestConfig {
+        fn try_from_toml(val: toml::Value) -> Option<Self> {
+            Some(Self::from_toml(val))
+        }
 
-        let mut config: TestConfig = files::load_from_yaml(YAML_PATH);
+        fn try_from_ini(ini: ini::Ini) -> Option<Self> {
+            Some(Self::from_ini(ini))
+        }
 
-        println!("{:?}", config);
+        fn try_from_properties(props: properties::Properties) -> Option<Self> {
+            Some(Self::from_properties(props))
+        }
 
-        change_config(&mut config);
-    }
+        fn try_from_env_map(vars: HashMap<String, String>) -> Option<Self> {
+            Some(Self::from_env_map(vars))
+        }
 
-    #[test]
-    fn json_test() {
-        // loads from json; could use files::load_from_file(),
-        // but since we already know the filetype, it's better to just do this
+        #[cfg(feature = "xml")]
+        fn try_from_xml(root: xmltree::Element) -> Option<Self> {
+            Some(Self::from_xml(root))
+        }
 
-        let mut config: TestConfig = files::load_from_json(JSON_PATH);
+        #[cfg(feature = "rmp")]
+        fn try_from_msgpack(val: rmpv::Value) -> Option<Self> {
+            Some(Self::from_msgpack(val))
+        }
 
-        println!("{:?}", config);
+        #[cfg(feature = "cbor")]
+        fn try_from_cbor(val: ciborium::Value) -> Option<Self> {
+            Some(Self::from_cbor(val))
+        }
 
-        change_config(&mut config);
+        #[cfg(feature = "bincode")]
+        fn try_from_binary(
+            bytes: Vec<u8>,
+        ) -> Option<std::result::Result<Self, Box<dyn std::error::Error + Send + Sync>>> {
+            Some(Self::from_bytes(&bytes).map_err(Into::into))
+        }
+    }
 
-        // saving both yaml and json but idc don't want to copy one line of code
-        config.save_json(JSON_PATH).expect("Unable to save");
+    impl MergeConfig for TestConfig {
+        fn merge(&mut self, other: Self) {
+            self.test = other.test;
+        }
     }
 
-    #[test]
-    fn file_test() {
-        let mut config: TestConfig =
-            files::load_from_file(YAML_PATH).expect("Unable to load from file");
+    #[cfg(feature = "bincode")]
+    impl BinaryConfig for TestConfig {}
 
-        println!("{:?}", config);
+    #[cfg(feature = "cbor")]
+    impl CborConfig for TestConfig {
+        fn from_cbor(val: ciborium::Value) -> Self {
+            let map = val.into_map().unwrap();
+            let (_, test) = map
+                .into_iter()
+                .find(|(k, _)| k.as_text() == Some("test"))
+                .unwrap();
 
-        change_config(&mut config);
+            Self {
+                test: test.as_bool().unwrap(),
+            }
+        }
+
+        fn save_cbor(&self, path: &str) -> Result<()> {
+            let val = ciborium::Value::Map(vec![("test".into(), self.test.into())]);
+
+            fs::write(path, cbor::to_canonical_vec(&val)?)
+        }
     }
 
-    // swaps the `test` variable value and saves
-    fn change_config(config: &mut TestConfig) {
-        config.test = !config.test;
+    #[cfg(feature = "rmp")]
+    impl MsgpackConfig for TestConfig {
+        fn from_msgpack(val: rmpv::Value) -> Self {
+            Self {
+                test: val["test"].as_bool().unwrap(),
+            }
+        }
 
-        config.save_yaml(YAML_PATH).expect("Unable to save");
+        fn save_msgpack(&self, path: &str) -> Result<()> {
+            let val = rmpv::Value::Map(vec![("test".into(), self.test.into())]);
 
-        println!("{:?}", config);
+            let mut data = Vec::new();
+            rmpv::encode::write_value(&mut data, &val)?;
+            fs::write(path, data)
+        }
+    }
+
+    impl EnvFileConfig for TestConfig {
+        fn from_env_map(vars: HashMap<String, String>) -> Self {
+            Self {
+                test: vars["TEST"] == "true",
+            }
+        }
+    }
+
+    #[cfg(feature = "xml")]
+    impl XmlConfig for TestConfig {
+        fn from_xml(root: xmltree::Element) -> Self {
+            Self {
+                test: xml::to_json(&root)["test"] == "true",
+            }
+        }
+
+        fn save_xml(&self, path: &str) -> Result<()> {
+            let mut test = xmltree::Element::new("test");
+            test.children
+                .push(xmltree::XMLNode::Text(self.test.to_string()));
+
+            let mut root = xmltree::Element::new("config");
+            root.children.push(xmltree::XMLNode::Element(test));
+
+            root.write(fs::File::create(path)?)
+                .map_err(std::io::Error::other)
+        }
+    }
+
+    // path to test files
+    const YAML_PATH: &str = "testing/test.yml";
+    const JSON_PATH: &str = "testing/test.json";
+    const TOML_PATH: &str = "testing/test.toml";
+    const INI_PATH: &str = "testing/test.ini";
+    const PROPERTIES_PATH: &str = "testing/test.properties";
+    const DOTENV_PATH: &str = "testing/.env";
+    #[cfg(feature = "json5")]
+    const JSON5_PATH: &str = "testing/test.json5";
+    #[cfg(feature = "xml")]
+    const XML_PATH: &str = "testing/test.xml";
+    #[cfg(feature = "cbor")]
+    const CBOR_PATH: &str = "testing/test.cbor";
+    #[cfg(feature = "rmp")]
+    const MSGPACK_PATH: &str = "testing/test.msgpack";
+
+    // saves go to the temp dir so the fixtures stay untouched while tests run in parallel
+    fn output_path(file_name: &str) -> String {
+        env::temp_dir()
+            .join(format!("rsconfig_{}", file_name))
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    #[test]
+    fn args_test() {
+        // under normal test command (cargo test --package rsconfig --lib -- tests --nocapture),
+        // this will always create `config` with `test` as false
+
+        let mut config = TestConfig::from_current_args();
+
+        println!("{:?}", config);
+
+        change_config(&mut config);
+
+        // the program path never shows up as a flag
+        let program = env::args().next().unwrap();
+        assert!(!quick::FlagConfig::from_current_args()
+            .long_args()
+            .any(|flag| flag == program));
+
+        #[cfg(unix)]
+        {
+            use std::{ffi::OsString, os::unix::ffi::OsStringExt};
+
+            let args = vec![
+                OsString::from("--name=caf\u{e9}"),
+                OsString::from_vec(b"--bad\xff".to_vec()),
+            ];
+            let flags = quick::FlagConfig::from_args_os(args);
+            assert_eq!(
+                flags.iter().collect::<Vec<_>>(),
+                ["name=caf\u{e9}", "bad\u{fffd}"]
+            );
+        }
+    }
+
+    #[test]
+    fn yaml_test() {
+        // loads from yaml; could use files::load_from_file(),
+        // but since we already know the filetype, it's better to just do this
+
+        let mut config: TestConfig = files::load_from_yaml(YAML_PATH);
+
+        println!("{:?}", config);
+
+        change_config(&mut config);
+    }
+
+    #[test]
+    fn json_test() {
+        // loads from json; could use files::load_from_file(),
+        // but since we already know the filetype, it's better to just do this
+
+        let mut config: TestConfig = files::load_from_json(JSON_PATH);
+
+        println!("{:?}", config);
+
+        change_config(&mut config);
+
+        // saving both yaml and json but idc don't want to copy one line of code
+        config
+            .save_json(&output_path("test.json"))
+            .expect("Unable to save");
+    }
+
+    #[test]
+    fn file_test() {
+        let mut config: TestConfig =
+            files::load_from_file(YAML_PATH).expect("Unable to load from file");
+
+        println!("{:?}", config);
+
+        change_config(&mut config);
+    }
+
+    // swaps the `test` variable value and saves
+    fn change_config(config: &mut TestConfig) {
+        config.test = !config.test;
+
+        config
+            .save_yaml(&output_path("test.yml"))
+            .expect("Unable to save");
+
+        println!("{:?}", config);
+    }
+
+    // counts how many times it's built from a file so shared loads can be checked
+    struct SharedConfig {
+        test: bool,
+    }
+
+    static SHARED_READS: AtomicUsize = AtomicUsize::new(0);
+
+    impl YamlConfig for SharedConfig {
+        fn from_yaml(yaml: Vec<yaml_rust::Yaml>) -> Self {
+            SHARED_READS.fetch_add(1, Ordering::SeqCst);
+
+            // give the other threads time to pile up behind the load
+            thread::sleep(Duration::from_millis(20));
+
+            Self {
+                test: yaml[0]["test"].as_bool().unwrap(),
+            }
+        }
+
+        fn save_yaml(&self, _path: &str) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    impl JsonConfig for SharedConfig {
+        fn from_json(val: Value) -> Self {
+            Self {
+                test: val["test"].as_bool().unwrap(),
+            }
+        }
+
+        fn save_json(&self, _path: &str) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    impl FileConfig for SharedConfig {}
+
+    #[test]
+    fn shared_load_test() {
+        let barrier = Arc::new(Barrier::new(16));
+
+        let handles: Vec<_> = (0..16)
+            .map(|_| {
+                let barrier = barrier.clone();
+
+                thread::spawn(move || {
+                    barrier.wait();
+                    once::load_shared::<SharedConfig>(YAML_PATH).expect("Unable to load")
+                })
+            })
+            .collect();
+
+        let configs: Vec<Arc<SharedConfig>> =
+            handles.into_iter().map(|h| h.join().unwrap()).collect();
+
+        assert!(configs[0].test);
+        assert!(configs.iter().all(|c| Arc::ptr_eq(c, &configs[0])));
+        assert_eq!(SHARED_READS.load(Ordering::SeqCst), 1);
+
+        // cached until invalidated
+        let cached = once::load_shared::<SharedConfig>(YAML_PATH).unwrap();
+        assert!(Arc::ptr_eq(&cached, &configs[0]));
+        assert_eq!(SHARED_READS.load(Ordering::SeqCst), 1);
+
+        once::invalidate(YAML_PATH);
+
+        let reloaded = once::load_shared::<SharedConfig>(YAML_PATH).unwrap();
+        assert!(!Arc::ptr_eq(&reloaded, &configs[0]));
+        assert_eq!(SHARED_READS.load(Ordering::SeqCst), 2);
+    }
+
+    // the loader blocks on the fifo until it's written, so every caller is waiting on the same failed load
+    #[cfg(unix)]
+    #[test]
+    fn shared_load_failure_test() {
+        let path = output_path("shared_failure.yml");
+        let _ = fs::remove_file(&path);
+        let status = std::process::Command::new("mkfifo")
+            .arg(&path)
+            .status()
+            .expect("Unable to run mkfifo");
+        assert!(status.success());
+
+        let barrier = Arc::new(Barrier::new(8));
+        let handles: Vec<_> = (0..8)
+            .map(|_| {
+                let barrier = barrier.clone();
+                let path = path.clone();
+
+                thread::spawn(move || {
+                    barrier.wait();
+                    once::load_shared::<SharedConfig>(&path)
+                })
+            })
+            .collect();
+
+        thread::sleep(Duration::from_millis(100));
+        fs::write(&path, "test: [unclosed").expect("Unable to write the fifo");
+
+        let errors: Vec<Arc<ConfigError>> = handles
+            .into_iter()
+            .map(|h| h.join().unwrap().err().expect("The load should fail"))
+            .collect();
+
+        assert!(matches!(*errors[0], ConfigError::YamlParse { .. }));
+        assert!(errors.iter().all(|e| Arc::ptr_eq(e, &errors[0])));
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    // panics on its first load only
+    struct PanickyConfig;
+
+    static PANICKY_READS: AtomicUsize = AtomicUsize::new(0);
+
+    impl YamlConfig for PanickyConfig {
+        fn from_yaml(_yaml: Vec<yaml_rust::Yaml>) -> Self {
+            if PANICKY_READS.fetch_add(1, Ordering::SeqCst) == 0 {
+                thread::sleep(Duration::from_millis(50));
+                panic!("first load fails");
+            }
+
+            Self
+        }
+
+        fn save_yaml(&self, _path: &str) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    impl JsonConfig for PanickyConfig {
+        fn from_json(_val: Value) -> Self {
+            Self
+        }
+
+        fn save_json(&self, _path: &str) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    impl FileConfig for PanickyConfig {}
+
+    #[test]
+    fn shared_load_panic_test() {
+        let loader = thread::spawn(|| once::load_shared::<PanickyConfig>(YAML_PATH));
+
+        // starts waiting while the first load is still running
+        thread::sleep(Duration::from_millis(10));
+        let waiter = thread::spawn(|| once::load_shared::<PanickyConfig>(YAML_PATH));
+
+        assert!(loader.join().is_err());
+        assert!(waiter.join().unwrap().is_ok());
+        assert_eq!(PANICKY_READS.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn flag_order_test() {
+        let cases: [&[&str]; 4] = [
+            &[],
+            &["prog", "--b", "--a", "--b"],
+            &["--verbose", "file.txt", "--key:value", "--quiet"],
+            &["--z", "--y", "--x", "--y", "--z"],
+        ];
+
+        for case in cases {
+            let args: Vec<String> = case.iter().map(|s| s.to_string()).collect();
+            let flags = quick::FlagConfig::from_env_args(args.clone());
+
+            // occurrence order, duplicates included
+            let expected: Vec<String> = args
+                .into_iter()
+                .filter(|a| a.starts_with("--") && !a.contains(':'))
+                .collect();
+            assert_eq!(flags.long_args().collect::<Vec<_>>(), expected);
+
+            // parse -> canonicalize -> parse is a fixed point
+            let reparsed = quick::FlagConfig::from_env_args(flags.canonical_args());
+            assert_eq!(reparsed, flags);
+            assert_eq!(reparsed.canonical_args(), flags.canonical_args());
+        }
+    }
+
+    #[test]
+    fn harvest_comments_test() {
+        let docs = docs::harvest_comments("testing/commented.yml").expect("Unable to read file");
+
+        let expected: HashMap<String, String> = [
+            (
+                "server",
+                "settings for the http server\napplies to every listener",
+            ),
+            ("server.port", "port the server listens on\nmust be free"),
+            ("server.tls.enabled", "no certs in dev"),
+            ("server.tls.cert path", "where the certificate lives"),
+            ("script", "startup script"),
+        ]
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+
+        assert_eq!(docs, expected);
+
+        // with blank lines allowed, the orphaned block documents `tls`
+        let options = docs::HarvestOptions {
+            attach_across_blank_lines: true,
+        };
+        let docs = docs::harvest_comments_with("testing/commented.yml", &options)
+            .expect("Unable to read file");
+
+        assert_eq!(docs["server.tls"], "orphaned by the blank line below");
+        assert!(!docs.contains_key("hosts"));
+
+        // a byte order mark doesn't hide the first block
+        let path = output_path("commented_bom.yml");
+        let mut data = b"\xEF\xBB\xBF".to_vec();
+        data.extend(fs::read("testing/commented.yml").unwrap());
+        fs::write(&path, data).unwrap();
+        assert_eq!(docs::harvest_comments(&path).unwrap(), expected);
+
+        let missing = output_path("missing_commented.yml");
+        match docs::harvest_comments(&missing) {
+            Err(ConfigError::Io { path, .. }) => assert_eq!(path, missing),
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn fd_test() {
+        use std::io::Write;
+
+        let (reader, mut writer) = std::io::pipe().unwrap();
+
+        let feeder = thread::spawn(move || {
+            writer.write_all(b"test: true\n").unwrap();
+        });
+
+        let config: TestConfig =
+            files::load_from_fd(&reader, files::ConfigFormat::Yaml).expect("Unable to load");
+        feeder.join().unwrap();
+
+        assert!(config.test);
+
+        // the descriptor stays open, but everything has been read from it
+        let err = files::load_from_fd::<TestConfig>(&reader, files::ConfigFormat::Yaml)
+            .err()
+            .unwrap();
+        match &err {
+            ConfigError::Io { path, source } => {
+                assert!(path.to_string_lossy().starts_with("file descriptor "));
+                assert_eq!(source.kind(), std::io::ErrorKind::UnexpectedEof);
+            }
+            e => panic!("expected an io error, got {}", e),
+        }
+
+        // parse errors name the descriptor too
+        let (reader, mut writer) = std::io::pipe().unwrap();
+        writer.write_all(b"{\"test\": }").unwrap();
+        drop(writer);
+
+        let err = files::load_from_fd::<TestConfig>(&reader, files::ConfigFormat::Json)
+            .err()
+            .unwrap();
+        assert!(matches!(err, ConfigError::JsonParse { .. }));
+        assert!(err.path().to_string_lossy().starts_with("file descriptor "));
+
+        // piped data is decoded like files, with a byte order mark or as UTF-16
+        let mut utf16 = vec![0xFF, 0xFE];
+        utf16.extend("{\"test\": true}".encode_utf16().flat_map(u16::to_le_bytes));
+
+        for (data, format) in [
+            (
+                b"\xEF\xBB\xBFtest: true\n".to_vec(),
+                files::ConfigFormat::Yaml,
+            ),
+            (utf16, files::ConfigFormat::Json),
+        ] {
+            let (reader, mut writer) = std::io::pipe().unwrap();
+            writer.write_all(&data).unwrap();
+            drop(writer);
+
+            let config: TestConfig = files::load_from_fd(&reader, format).expect("Unable to load");
+            assert!(config.test);
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn config_fd_arg_test() {
+        let parse = |args: &[&str]| {
+            quick::ConfigFdArg::from_env_args(args.iter().map(|s| s.to_string()).collect()).0
+        };
+
+        assert_eq!(parse(&["prog", "--config-fd", "3"]), Some(3));
+        assert_eq!(parse(&["prog", "--config-fd=4", "--verbose"]), Some(4));
+        assert_eq!(parse(&["prog", "--config-fdx", "5"]), None);
+        assert_eq!(parse(&["prog", "--config-fd"]), None);
+        assert_eq!(parse(&["prog", "--config-fd", "-1"]), None);
+        // the last one wins, like `--config` in files::path_from_args
+        assert_eq!(
+            parse(&["prog", "--config-fd", "3", "--config-fd=5"]),
+            Some(5)
+        );
+        assert_eq!(parse(&["prog", "--config-fd=3", "--config-fd", "x"]), None);
+        assert_eq!(
+            parse(&["prog", "--config-fd=3", "--", "--config-fd=5"]),
+            Some(3)
+        );
+        assert_eq!(parse(&["prog"]), None);
+    }
+
+    #[test]
+    fn flag_view_test() {
+        let args = [
+            "prog",
+            "--verbose",
+            "--level=3",
+            "--level=4=x",
+            "input",
+            "--key:value",
+        ];
+        let flags = quick::FlagView::parse(args.into_iter());
+
+        assert!(flags.has("verbose"));
+        assert!(flags.has("level"));
+        assert!(!flags.has("key"));
+        assert!(!flags.has("input"));
+        assert_eq!(flags.value_of("level"), Some("4=x"));
+        assert_eq!(flags.value_of("verbose"), None);
+
+        // borrowed and owned parsing agree
+        let owned = quick::FlagConfig::from_env_args(args.iter().map(|s| s.to_string()).collect());
+        assert_eq!(flags.to_flag_config(), owned);
+        assert_eq!(quick::FlagConfig::from_env_args_ref(&args), owned);
+
+        // the provided method falls back to from_env_args
+        let config = TestConfig::from_env_args_ref(&["prog", "test"]);
+        assert!(config.test);
+    }
+
+    #[cfg(feature = "buildinfo")]
+    #[test]
+    fn build_info_test() {
+        let vars: HashMap<&str, &str> = [
+            ("RSCONFIG_BUILD_VERSION", "1.2.3"),
+            ("RSCONFIG_BUILD_GIT_HASH", "abc1234"),
+            ("RSCONFIG_BUILD_FEATURES", "default,tls-rustls"),
+            ("RSCONFIG_BUILD_TARGET", "x86_64-unknown-linux-gnu"),
+            ("RSCONFIG_BUILD_TIMESTAMP", "1700000000"),
+        ]
+        .into_iter()
+        .collect();
+
+        let info = buildinfo::BuildInfo::from_vars(|name| vars.get(name).map(|v| v.to_string()));
+
+        assert_eq!(info.features, ["default", "tls-rustls"]);
+        assert_eq!(
+            info.to_string(),
+            "version 1.2.3 (git abc1234) target x86_64-unknown-linux-gnu features [default, tls-rustls] built at 1700000000"
+        );
+        assert_eq!(info.to_json()["timestamp"], 1700000000);
+
+        // Cargo's list keeps the names as they were declared, the older variables are a lossy guess
+        let vars = |vars: &[(&str, &str)]| {
+            vars.iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect::<Vec<_>>()
+                .into_iter()
+        };
+        assert_eq!(
+            buildinfo::feature_names(vars(&[
+                ("CARGO_FEATURE_MY_FEATURE", "1"),
+                ("CARGO_CFG_FEATURE", "my_feature,default"),
+            ])),
+            ["default", "my_feature"]
+        );
+        assert_eq!(
+            buildinfo::feature_names(vars(&[
+                ("CARGO_FEATURE_MY_FEATURE", "1"),
+                ("CARGO_FEATURE_DEFAULT", "1"),
+                ("PATH", "/bin"),
+            ])),
+            ["default", "my-feature"]
+        );
+
+        // this crate's build script doesn't call emit, so everything is empty
+        let empty = build_info!();
+        assert_eq!(empty, buildinfo::BuildInfo::default());
+        assert_eq!(
+            empty.to_string(),
+            "version unknown (git unknown) target unknown features [] built at unknown"
+        );
+    }
+
+    #[test]
+    fn toml_test() {
+        let config: TestConfig = files::load_from_toml(TOML_PATH);
+        assert!(config.test);
+
+        let config: TestConfig =
+            files::load_from_file(TOML_PATH).expect("Unable to load from file");
+        assert!(config.test);
+
+        // types that don't opt in can't be loaded from toml through load_from_file
+        assert!(files::load_from_file::<SharedConfig>(TOML_PATH).is_err());
+    }
+
+    #[derive(Debug, PartialEq)]
+    struct ServerConfig {
+        name: String,
+        enabled: bool,
+        port: i64,
+    }
+
+    impl TomlConfig for ServerConfig {
+        fn from_toml(val: toml::Value) -> Self {
+            Self {
+                name: val["name"].as_str().unwrap().to_string(),
+                enabled: val["enabled"].as_bool().unwrap(),
+                port: val["server"]["port"].as_integer().unwrap(),
+            }
+        }
+
+        fn save_toml(&self, path: &str) -> Result<()> {
+            let mut server = toml::Table::new();
+            server.insert("port".to_string(), toml::Value::from(self.port));
+
+            let mut table = toml::Table::new();
+            table.insert("name".to_string(), toml::Value::from(self.name.as_str()));
+            table.insert("enabled".to_string(), toml::Value::from(self.enabled));
+            table.insert("server".to_string(), toml::Value::Table(server));
+
+            fs::write(path, table.to_string())
+        }
+    }
+
+    #[test]
+    fn toml_round_trip_test() {
+        let path = output_path("server.toml");
+        let config = ServerConfig {
+            name: "app".to_string(),
+            enabled: true,
+            port: 8080,
+        };
+
+        config.save_toml(&path).expect("Unable to save");
+
+        let loaded: ServerConfig = files::load_from_toml(&path);
+        assert_eq!(loaded, config);
+    }
+
+    #[test]
+    fn ini_test() {
+        let config: TestConfig = files::load_from_ini(INI_PATH);
+        assert!(config.test);
+
+        let config: TestConfig = files::load_from_file(INI_PATH).expect("Unable to load from file");
+        assert!(config.test);
+
+        config
+            .save_ini(&output_path("test.ini"))
+            .expect("Unable to save");
+    }
+
+    #[test]
+    fn ini_parse_test() {
+        let data = fs::read_to_string(INI_PATH).unwrap();
+        let ini: ini::Ini = data.parse().expect("Unable to parse");
+
+        // keys outside any section
+        assert_eq!(ini.get("", "test"), Some("true"));
+
+        // duplicate keys keep every value, the last one wins
+        assert_eq!(ini.get_all("server", "host"), ["a.example", "b.example"]);
+        assert_eq!(ini.get("server", "host"), Some("b.example"));
+
+        // `:` separators, and inline ; or # stay part of the value
+        assert_eq!(ini.get("server", "port"), Some("8080"));
+        assert_eq!(
+            ini.get("paths", "data"),
+            Some("/var/lib/app ; not a comment")
+        );
+        assert_eq!(ini.get("paths", "url"), Some("http://x/#frag"));
+
+        assert_eq!(ini.sections().collect::<Vec<_>>(), ["", "server", "paths"]);
+
+        // round trip through Display
+        let reparsed: ini::Ini = ini.to_string().parse().unwrap();
+        assert_eq!(reparsed, ini);
+
+        let err = "[broken\n".parse::<ini::Ini>().unwrap_err();
+        assert_eq!(err.line, 1);
+        assert!("key without value".parse::<ini::Ini>().is_err());
+    }
+
+    #[test]
+    fn properties_test() {
+        let config: TestConfig = files::load_from_properties(PROPERTIES_PATH);
+        assert!(config.test);
+
+        let config: TestConfig =
+            files::load_from_file(PROPERTIES_PATH).expect("Unable to load from file");
+        assert!(config.test);
+
+        let path = output_path("test.properties");
+        config.save_properties(&path).expect("Unable to save");
+
+        let loaded: TestConfig = files::load_from_properties(&path);
+        assert!(loaded.test);
+    }
+
+    #[test]
+    fn try_load_test() {
+        let missing = output_path("missing.conf");
+        let is_io = |e: ConfigError| matches!(e, ConfigError::Io { .. });
+        assert!(is_io(
+            files::try_load_from_toml::<TestConfig>(&missing)
+                .err()
+                .unwrap()
+        ));
+        assert!(is_io(
+            files::try_load_from_ini::<TestConfig>(&missing)
+                .err()
+                .unwrap()
+        ));
+        assert!(is_io(
+            files::try_load_from_properties::<TestConfig>(&missing)
+                .err()
+                .unwrap()
+        ));
+        assert!(is_io(
+            files::try_load_from_dotenv::<TestConfig>(&missing)
+                .err()
+                .unwrap()
+        ));
+
+        let broken = |name: &str, data: &str| {
+            let path = output_path(name);
+            fs::write(&path, data).unwrap();
+            path
+        };
+        let format_of = |e: ConfigError| match e {
+            ConfigError::Parse { format, .. } => format,
+            e => panic!("expected a parse error, got {}", e),
+        };
+
+        let path = broken("broken.toml", "test = ");
+        assert_eq!(
+            format_of(
+                files::try_load_from_toml::<TestConfig>(&path)
+                    .err()
+                    .unwrap()
+            ),
+            "TOML"
+        );
+        let path = broken("broken.ini", "[section]\nno separator");
+        assert_eq!(
+            format_of(files::try_load_from_ini::<TestConfig>(&path).err().unwrap()),
+            "INI"
+        );
+        let path = broken("broken.properties", "key = \\uZZZZ");
+        assert_eq!(
+            format_of(
+                files::try_load_from_properties::<TestConfig>(&path)
+                    .err()
+                    .unwrap()
+            ),
+            "properties"
+        );
+        let path = broken("broken.env", "not a variable");
+        assert_eq!(
+            format_of(
+                files::try_load_from_dotenv::<TestConfig>(&path)
+                    .err()
+                    .unwrap()
+            ),
+            "dotenv"
+        );
+
+        #[cfg(feature = "json5")]
+        {
+            assert!(is_io(
+                files::try_load_from_json5::<TestConfig>(&missing)
+                    .err()
+                    .unwrap()
+            ));
+
+            let path = broken("broken.json5", "{test: }");
+            assert_eq!(
+                format_of(
+                    files::try_load_from_json5::<TestConfig>(&path)
+                        .err()
+                        .unwrap()
+                ),
+                "JSON5"
+            );
+        }
+
+        assert!(
+            files::try_load_from_toml::<TestConfig>(TOML_PATH)
+                .unwrap()
+                .test
+        );
+    }
+
+    #[test]
+    fn properties_parse_test() {
+        let data = fs::read_to_string(PROPERTIES_PATH).unwrap();
+        let props: properties::Properties = data.parse().expect("Unable to parse");
+
+        // `=`, `:` and whitespace separators
+        assert_eq!(props.get("server.host"), Some("localhost"));
+        assert_eq!(props.get("server.port"), Some("8080"));
+        assert_eq!(props.get("server.name"), Some("demo server"));
+
+        // continuation lines drop their leading whitespace
+        assert_eq!(props.get("servers"), Some("a.example,b.example,c.example"));
+
+        // escapes, including a surrogate pair
+        assert_eq!(props.get("greeting"), Some("café ☕ 😀"));
+        assert_eq!(props.get("key with spaces"), Some("x=y"));
+        assert_eq!(props.get("path"), Some("C:\\data\\app"));
+        assert_eq!(props.get("empty"), Some(""));
+
+        // a repeated key keeps its first position with the last value
+        assert_eq!(props.get("mode"), Some("prod"));
+        let keys: Vec<&str> = props.iter().map(|(k, _)| k).collect();
+        assert_eq!(keys[1], "mode");
+
+        // round trip through Display, which writes keys in the same order
+        let written = props.to_string();
+        assert!(written.is_ascii());
+        let reparsed: properties::Properties = written.parse().unwrap();
+        assert_eq!(reparsed, props);
+        assert_eq!(reparsed.to_string(), written);
+
+        let err = "ok=1\nbad=\\u12\n"
+            .parse::<properties::Properties>()
+            .unwrap_err();
+        assert_eq!(err.line, 2);
+        assert!("lone=\\uD83D".parse::<properties::Properties>().is_err());
+        // a sign isn't a hex digit
+        let err = "signed=\\u+123"
+            .parse::<properties::Properties>()
+            .unwrap_err();
+        assert!(err.message.contains("malformed"), "{}", err.message);
+    }
+
+    #[cfg(feature = "json5")]
+    #[test]
+    fn json5_test() {
+        let config: TestConfig = files::load_from_json5(JSON5_PATH);
+        assert!(config.test);
+
+        let config: TestConfig =
+            files::load_from_file(JSON5_PATH).expect("Unable to load from file");
+        assert!(config.test);
+    }
+
+    #[cfg(feature = "rmp")]
+    #[test]
+    fn msgpack_test() {
+        let config: TestConfig = files::load_from_msgpack(MSGPACK_PATH);
+        assert!(config.test);
+
+        let config: TestConfig =
+            files::try_load_from_msgpack(MSGPACK_PATH).expect("Unable to load");
+        assert!(config.test);
+
+        assert!(matches!(
+            files::try_load_from_msgpack::<TestConfig>(output_path("missing.msgpack")),
+            Err(ConfigError::Io { .. })
+        ));
+
+        let config: TestConfig =
+            files::load_from_file(MSGPACK_PATH).expect("Unable to load from file");
+        assert!(config.test);
+
+        // .mp is accepted too
+        let path = output_path("test.mp");
+        config.save_msgpack(&path).expect("Unable to save");
+
+        let loaded: TestConfig = files::load_from_file(&path).expect("Unable to load from file");
+        assert!(loaded.test);
+
+        // blobs that don't come from a file
+        let bytes = fs::read(MSGPACK_PATH).unwrap();
+        let config: TestConfig = files::from_msgpack_bytes(&bytes).expect("Unable to decode");
+        assert!(config.test);
+
+        let err = files::from_msgpack_bytes::<TestConfig>(&bytes[..bytes.len() - 1]).unwrap_err();
+        assert!(matches!(
+            err,
+            ConfigError::Parse {
+                format: "MessagePack",
+                ..
+            }
+        ));
+        assert!(err.to_string().starts_with("Failed to parse MessagePack: "));
+
+        let mut trailing = bytes.clone();
+        trailing.push(0xC0);
+        assert!(files::from_msgpack_bytes::<TestConfig>(&trailing).is_err());
+    }
+
+    #[cfg(feature = "cbor")]
+    #[test]
+    fn cbor_test() {
+        use ciborium::Value;
+
+        // the fixture is an indefinite-length map holding an indefinite-length byte string
+        let config: TestConfig = files::load_from_cbor(CBOR_PATH);
+        assert!(config.test);
+
+        let config: TestConfig =
+            files::load_from_file(CBOR_PATH).expect("Unable to load from file");
+        assert!(config.test);
+
+        let path = output_path("test.cbor");
+        config.save_cbor(&path).expect("Unable to save");
+        let loaded: TestConfig = files::load_from_file(&path).expect("Unable to load from file");
+        assert!(loaded.test);
+
+        let val: Value = ciborium::from_reader(fs::read(CBOR_PATH).unwrap().as_slice()).unwrap();
+        assert_eq!(val.as_map().unwrap()[1].1, Value::Bytes(vec![1, 2, 3]));
+
+        // canonical output doesn't depend on insertion order, at any depth
+        let nested = |first: (&str, u64), second: (&str, u64)| {
+            Value::Map(vec![
+                (
+                    "zeta".into(),
+                    Value::Array(vec![Value::Map(vec![
+                        (first.0.into(), first.1.into()),
+                        (second.0.into(), second.1.into()),
+                    ])]),
+                ),
+                ("a".into(), 1.5.into()),
+                (Value::Integer(10.into()), Value::Bytes(vec![0; 3])),
+            ])
+        };
+
+        let a = cbor::to_canonical_vec(&nested(("b", 1), ("a", 500))).unwrap();
+        let b = cbor::to_canonical_vec(&nested(("a", 500), ("b", 1))).unwrap();
+        assert_eq!(a, b);
+
+        // keys sort by their encoding: the integer 10 (0x0a) before "a" (0x61 0x61) before "zeta" (0x64 ...)
+        assert_eq!(&a[..4], [0xA3, 0x0A, 0x43, 0x00]);
+        // shortest forms: 1.5 as a half float, 500 as a two byte integer
+        assert!(a.windows(3).any(|w| w == [0xF9, 0x3E, 0x00]));
+        assert!(a.windows(3).any(|w| w == [0x19, 0x01, 0xF4]));
+
+        // truncated data is an error, not a panic
+        let bytes = fs::read(CBOR_PATH).unwrap();
+        fs::write(&path, &bytes[..bytes.len() - 1]).unwrap();
+        let err = files::try_load_from_cbor::<TestConfig>(&path).unwrap_err();
+        assert!(matches!(err, ConfigError::Parse { format: "CBOR", .. }));
+    }
+
+    #[cfg(feature = "bincode")]
+    #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+    struct RoutingConfig {
+        name: String,
+        // one list of (prefix, weight) routes per region
+        regions: Vec<Vec<(String, u32)>>,
+        fallback: Option<Vec<u64>>,
+    }
+
+    #[cfg(feature = "bincode")]
+    impl BinaryConfig for RoutingConfig {}
+
+    #[cfg(feature = "bincode")]
+    #[test]
+    fn binary_test() {
+        let config = RoutingConfig {
+            name: "edge".to_string(),
+            regions: vec![
+                vec![("/api".to_string(), 3), ("/static".to_string(), 1)],
+                vec![],
+                vec![("/".to_string(), 10)],
+            ],
+            fallback: Some(vec![1, u64::MAX]),
+        };
+
+        let path = output_path("routing.bin");
+        config.save_binary(&path).expect("Unable to save");
+
+        let loaded: RoutingConfig = files::load_from_binary(&path).expect("Unable to load");
+        assert_eq!(loaded, config);
+
+        // truncated and corrupt data are errors, not panics
+        let bytes = fs::read(&path).unwrap();
+        for len in 0..bytes.len() {
+            let err = RoutingConfig::from_bytes(&bytes[..len]).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        }
+
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        assert!(RoutingConfig::from_bytes(&trailing).is_err());
+
+        // a huge length prefix for the name
+        let mut corrupt = bytes;
+        corrupt[0] = 0xFF;
+        assert!(RoutingConfig::from_bytes(&corrupt).is_err());
+
+        fs::write(&path, &corrupt).unwrap();
+        assert!(matches!(
+            files::load_from_binary::<RoutingConfig>(&path),
+            Err(ConfigError::Parse {
+                format: "bincode",
+                ..
+            })
+        ));
+
+        assert!(matches!(
+            files::load_from_binary::<RoutingConfig>(&output_path("missing.bin")),
+            Err(ConfigError::Io { .. })
+        ));
+
+        // .bin files through load_from_file
+        let path = output_path("test.bin");
+        TestConfig { test: true }
+            .save_binary(&path)
+            .expect("Unable to save");
+
+        let config: TestConfig = files::load_from_file(&path).expect("Unable to load from file");
+        assert!(config.test);
+
+        // undecodable files are parse errors, not a missing binary format
+        for bytes in [&[][..], &[7]] {
+            fs::write(&path, bytes).unwrap();
+            assert!(matches!(
+                files::load_from_file::<TestConfig>(&path),
+                Err(ConfigError::Parse {
+                    format: "binary",
+                    ..
+                })
+            ));
+        }
+    }
+
+    #[cfg(feature = "xml")]
+    #[test]
+    fn xml_test() {
+        let mut config: TestConfig = files::load_from_xml(XML_PATH);
+        assert!(config.test);
+
+        let config2: TestConfig =
+            files::load_from_file(XML_PATH).expect("Unable to load from file");
+        assert!(config2.test);
+
+        // round trip through save_xml
+        let path = output_path("test.xml");
+        config.test = false;
+        config.save_xml(&path).expect("Unable to save");
+
+        let reloaded: TestConfig = files::try_load_from_xml(&path).expect("Unable to load");
+        assert!(!reloaded.test);
+    }
+
+    #[cfg(feature = "xml")]
+    #[test]
+    fn xml_mapping_test() {
+        let data = fs::read(XML_PATH).unwrap();
+        let root = xmltree::Element::parse(data.as_slice()).unwrap();
+
+        assert_eq!(
+            xml::to_json(&root),
+            serde_json::json!({
+                "@version": "2",
+                "test": "true",
+                "server": {"@port": "8080", "#text": "primary"},
+                "host": ["a.example", "b.example"],
+                "empty": "",
+            })
+        );
+
+        // malformed xml is an error, not a panic
+        let path = output_path("broken.xml");
+        fs::write(&path, "<config><test>").unwrap();
+
+        let err = files::try_load_from_xml::<TestConfig>(&path).err().unwrap();
+        assert!(matches!(err, ConfigError::Parse { format: "XML", .. }));
+    }
+
+    // exposes the parsed variables directly
+    struct EnvVars(HashMap<String, String>);
+
+    impl EnvFileConfig for EnvVars {
+        fn from_env_map(vars: HashMap<String, String>) -> Self {
+            Self(vars)
+        }
+    }
+
+    #[test]
+    fn dotenv_test() {
+        let config: TestConfig = files::load_from_dotenv(DOTENV_PATH);
+        assert!(config.test);
+
+        // a file literally named .env has no extension to split on
+        let config: TestConfig =
+            files::load_from_file(DOTENV_PATH).expect("Unable to load from file");
+        assert!(config.test);
+
+        let EnvVars(vars) = files::load_from_dotenv(DOTENV_PATH);
+        let expected: HashMap<String, String> = [
+            ("TEST", "true"),
+            ("EXPORTED", "yes"),
+            ("SINGLE", "raw \\n $value"),
+            ("DOUBLE", "line one\nline \"two\""),
+            ("MULTILINE", "first\nsecond"),
+            ("INLINE", "value"),
+            ("HASH", "a#b"),
+            ("EMPTY", ""),
+            ("SPACED", "padded value"),
+        ]
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+
+        assert_eq!(vars, expected);
+    }
+
+    #[test]
+    fn transaction_test() {
+        let dir = env::temp_dir().join("rsconfig_transaction");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+
+        let yaml = dir.join("config.yml");
+        let json = dir.join("config.json");
+        let manifest = dir.join("manifest.txt");
+
+        let config = TestConfig { test: true };
+
+        files::Transaction::new()
+            .stage_config(&config, &yaml, files::ConfigFormat::Yaml)
+            .stage_config(&config, &json, files::ConfigFormat::Json)
+            .stage(&manifest, "v1")
+            .commit()
+            .expect("Unable to commit");
+
+        let loaded: TestConfig = files::load_from_file(yaml.to_str().unwrap()).unwrap();
+        assert!(loaded.test);
+        let loaded: TestConfig = files::load_from_file(json.to_str().unwrap()).unwrap();
+        assert!(loaded.test);
+        assert_eq!(fs::read_to_string(&manifest).unwrap(), "v1");
+
+        // a failure while staging leaves every file untouched
+        let err = files::Transaction::new()
+            .stage(&manifest, "v2")
+            .stage(dir.join("missing").join("file.txt"), "x")
+            .commit()
+            .unwrap_err();
+
+        assert!(matches!(err, files::TransactionError::Staging { .. }));
+        assert_eq!(fs::read_to_string(&manifest).unwrap(), "v1");
+
+        // a failed rename (the target is a directory) doesn't stop the others
+        let blocked = dir.join("blocked");
+        fs::create_dir_all(blocked.join("inner")).unwrap();
+
+        let err = files::Transaction::new()
+            .stage(&manifest, "v3")
+            .stage(&blocked, "x")
+            .stage(dir.join("last.txt"), "last")
+            .commit()
+            .unwrap_err();
+
+        match err {
+            files::TransactionError::Partial(states) => {
+                let committed: Vec<bool> = states
+                    .iter()
+                    .map(|(_, state)| matches!(state, files::FileState::Committed))
+                    .collect();
+                assert_eq!(committed, [true, false, true]);
+                assert_eq!(states[1].0, blocked);
+            }
+            _ => panic!("expected a partial commit"),
+        }
+
+        assert_eq!(fs::read_to_string(&manifest).unwrap(), "v3");
+        assert_eq!(fs::read_to_string(dir.join("last.txt")).unwrap(), "last");
+
+        // no temporary files are left behind
+        let leftovers: Vec<_> = fs::read_dir(&dir)
+            .unwrap()
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| {
+                entry
+                    .file_name()
+                    .to_string_lossy()
+                    .ends_with(".rsconfig-tmp")
+            })
+            .collect();
+        assert!(leftovers.is_empty());
+    }
+
+    // the temporary files a transaction left in a directory
+    fn temp_files(dir: &Path) -> Vec<std::path::PathBuf> {
+        fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.to_string_lossy().ends_with(".rsconfig-tmp"))
+            .collect()
+    }
+
+    #[test]
+    fn transaction_fault_test() {
+        let dir = env::temp_dir().join("rsconfig_transaction_faults");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+
+        let paths = [dir.join("a.txt"), dir.join("b.txt"), dir.join("c.txt")];
+        let read_all = || {
+            paths
+                .each_ref()
+                .map(|path| fs::read_to_string(path).unwrap())
+        };
+        let stage = |transaction: files::Transaction<'static>, contents: &str| {
+            paths
+                .iter()
+                .fold(transaction, |t, path| t.stage(path, contents))
+        };
+        // runs `fault` instead of the rename of the `index`th staged file
+        let faulty = |index: usize, fault: fn() -> io::Result<()>| {
+            let mut renames = 0;
+
+            files::Transaction::with_rename(move |temp: &Path, path: &Path| {
+                renames += 1;
+
+                if renames - 1 == index {
+                    fault()
+                } else {
+                    fs::rename(temp, path)
+                }
+            })
+        };
+
+        stage(files::Transaction::new(), "old")
+            .commit()
+            .expect("Unable to commit");
+
+        // dying after the first rename leaves whole files, old or new, and the temporary files of the rest
+        // (unwinding skips the cleanup, like a crash would)
+        let crashed = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            stage(faulty(1, || panic!("injected crash")), "new").commit()
+        }));
+
+        assert!(crashed.is_err());
+        assert_eq!(read_all(), ["new", "old", "old"]);
+        assert_eq!(temp_files(&dir).len(), 2);
+
+        // committing again finishes the job
+        stage(files::Transaction::new(), "new")
+            .commit()
+            .expect("Unable to commit");
+        assert_eq!(read_all(), ["new", "new", "new"]);
+
+        for temp in temp_files(&dir) {
+            fs::remove_file(temp).unwrap();
+        }
+
+        // a failed rename keeps the old file and cleans up after itself
+        let err = stage(
+            faulty(0, || Err(io::Error::other("injected rename failure"))),
+            "newer",
+        )
+        .commit()
+        .unwrap_err();
+
+        match err {
+            files::TransactionError::Partial(states) => {
+                let committed: Vec<bool> = states
+                    .iter()
+                    .map(|(_, state)| matches!(state, files::FileState::Committed))
+                    .collect();
+                assert_eq!(committed, [false, true, true]);
+            }
+            e => panic!("expected a partial commit, got {}", e),
+        }
+        assert_eq!(read_all(), ["new", "newer", "newer"]);
+        assert!(temp_files(&dir).is_empty());
+
+        // threads saving the same file don't share temporary files
+        let handles: Vec<_> = (0..8)
+            .map(|i| {
+                let path = paths[0].clone();
+                thread::spawn(move || {
+                    files::Transaction::new()
+                        .stage(path, format!("thread {}", i))
+                        .commit()
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap().expect("Unable to commit");
+        }
+        assert!(fs::read_to_string(&paths[0])
+            .unwrap()
+            .starts_with("thread "));
+        assert!(temp_files(&dir).is_empty());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn requirements_test() {
+        use requirements::{Requirement, RequirementKind};
+
+        let dir = env::temp_dir().join("rsconfig_requirements");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+
+        let cert = dir.join("cert.pem");
+        fs::write(&cert, "cert").unwrap();
+
+        // holding a listener keeps its port occupied for the check
+        let occupied = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+
+        let config = serde_json::json!({
+            "tls": { "cert_path": cert, "key_path": dir.join("missing.pem") },
+            "storage": { "data_dir": dir, "cache_dir": cert },
+            "server": { "listen": "127.0.0.1:0", "admin": occupied.local_addr().unwrap().to_string() },
+            "tools": { "ffmpeg": "rsconfig-definitely-not-installed" },
+        });
+
+        let reqs = [
+            Requirement::file_readable("tls.cert_path"),
+            Requirement::file_readable("tls.key_path"),
+            Requirement::dir_writable("storage.data_dir"),
+            Requirement::dir_writable("storage.cache_dir"),
+            Requirement::port_bindable("server.listen"),
+            Requirement::port_bindable("server.admin"),
+            Requirement::executable_on_path("tools.ffmpeg"),
+            Requirement::file_readable("tls.ca_path"),
+            // skipped checks never fail
+            Requirement::executable_on_path("tools.ffmpeg").skip(true),
+        ];
+
+        let failures = requirements::verify(&config, &reqs);
+
+        let failed: Vec<(&str, RequirementKind)> = failures
+            .iter()
+            .map(|failure| (failure.path.as_str(), failure.kind))
+            .collect();
+        assert_eq!(
+            failed,
+            [
+                ("tls.key_path", RequirementKind::FileReadable),
+                ("storage.cache_dir", RequirementKind::DirWritable),
+                ("server.admin", RequirementKind::PortBindable),
+                ("tools.ffmpeg", RequirementKind::ExecutableOnPath),
+                ("tls.ca_path", RequirementKind::FileReadable),
+            ]
+        );
+
+        // failures carry the resolved value, or None when it's missing
+        assert_eq!(
+            failures[0].value.as_deref(),
+            Some(dir.join("missing.pem").to_str().unwrap())
+        );
+        assert_eq!(failures[4].value, None);
+        assert!(failures[3].to_string().contains("PATH"));
+
+        // the writability probe is cleaned up
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+
+        // port checks release the socket right away
+        let free = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let config = serde_json::json!({ "port": free.to_string() });
+        for _ in 0..2 {
+            assert!(
+                requirements::verify(&config, &[Requirement::port_bindable("port")]).is_empty()
+            );
+        }
+        std::net::TcpListener::bind(free).expect("port was left bound");
+
+        // concurrent checks of one directory don't trip over each other's probes
+        let config = serde_json::json!({ "dir": dir });
+        let threads: Vec<_> = (0..8)
+            .map(|_| {
+                let config = config.clone();
+                std::thread::spawn(move || {
+                    (0..50).all(|_| {
+                        requirements::verify(&config, &[Requirement::dir_writable("dir")])
+                            .is_empty()
+                    })
+                })
+            })
+            .collect();
+        assert!(threads.into_iter().all(|t| t.join().unwrap()));
+
+        // the builder checks requirements against the merged layers
+        let err = layers::ConfigBuilder::<TestConfig>::new()
+            .args(vec![format!("--tls.cert_path={}", cert.display())])
+            .require(reqs[..2].to_vec())
+            .merge()
+            .unwrap_err();
+        match &err {
+            layers::LayerError::Requirements(failures) => {
+                assert_eq!(failures.len(), 1);
+                assert_eq!(failures[0].path, "tls.key_path");
+            }
+            other => panic!("unexpected error {:?}", other),
+        }
+        assert!(err.to_string().contains("tls.key_path"));
+
+        let merged = layers::ConfigBuilder::<TestConfig>::new()
+            .args(vec![format!("--tls.cert_path={}", cert.display())])
+            .require([Requirement::file_readable("tls.cert_path")])
+            .merge()
+            .unwrap();
+        assert_eq!(merged.value["tls"]["cert_path"], cert.to_str().unwrap());
+
+        #[cfg(unix)]
+        {
+            let config = serde_json::json!({ "shell": "sh", "full": "/bin/sh" });
+            let reqs = [
+                Requirement::executable_on_path("shell"),
+                Requirement::executable_on_path("full"),
+            ];
+            assert!(requirements::verify(&config, &reqs).is_empty());
+        }
+    }
+
+    #[cfg(feature = "anonymize")]
+    #[test]
+    fn anonymize_test() {
+        use anonymize::ScrubRules;
+
+        let config = serde_json::json!({
+            "server": {
+                "host": "db.internal.example.com",
+                "url": "postgres://app@db.internal.example.com:5432/main",
+                "bind": "10.20.30.40",
+                "local": "127.0.0.1",
+                "v6": "2001:db8::17",
+                "port": 5432,
+                "tls": true,
+            },
+            "admins": ["ops@example.com", "alice@corp.example.org"],
+            "data_dir": "/home/alice/app/data.db",
+            "windows": "C:\\Users\\alice\\app.ini",
+            "credentials": { "db_password": "hunter2", "pin": 1234, "enabled": false },
+            "db.internal.example.com": { "weight": 3 },
+            "ticket": "see JIRA-4411 for details",
+        });
+
+        let rules = ScrubRules::default()
+            .pattern("ticket", r"JIRA-\d+")
+            .unwrap();
+        let (scrubbed, map) = anonymize::scrub(&config, &rules);
+
+        // deterministic, within a run and across runs
+        assert_eq!(
+            anonymize::scrub(&config, &rules),
+            (scrubbed.clone(), map.clone())
+        );
+        assert_eq!(map.get("hunter2"), Some("secret-551f"));
+
+        // a different salt gives different placeholders
+        let salted = ScrubRules {
+            salt: "private".to_string(),
+            ..rules.clone()
+        };
+        assert_ne!(anonymize::scrub(&config, &salted).0, scrubbed);
+
+        // the same hostname maps to the same placeholder everywhere, including keys and urls
+        let host = map.get("db.internal.example.com").unwrap();
+        assert_eq!(scrubbed["server"]["host"], host);
+        assert!(scrubbed["server"]["url"].as_str().unwrap().contains(host));
+        assert!(scrubbed.get(host).is_some());
+        assert_eq!(host.matches('.').count(), 3);
+
+        // no originals are left
+        let text = scrubbed.to_string();
+        for original in [
+            "example",
+            "alice",
+            "hunter2",
+            "10.20.30.40",
+            "2001:db8",
+            "JIRA",
+            "1234",
+        ] {
+            assert!(!text.contains(original), "{} leaked: {}", original, text);
+        }
+
+        // types and shapes are preserved
+        let bind: std::net::Ipv4Addr = scrubbed["server"]["bind"]
+            .as_str()
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert!(bind.is_private());
+        let v6: std::net::Ipv6Addr = scrubbed["server"]["v6"].as_str().unwrap().parse().unwrap();
+        assert_eq!(v6.segments()[0], 0xfd00);
+        assert_eq!(scrubbed["server"]["local"], "127.0.0.1");
+        assert_eq!(scrubbed["server"]["port"], 5432);
+        assert_eq!(scrubbed["server"]["tls"], true);
+        assert_eq!(scrubbed["credentials"]["enabled"], false);
+
+        let pin = scrubbed["credentials"]["pin"].as_u64().unwrap();
+        assert!((1000..10000).contains(&pin));
+
+        let email = scrubbed["admins"][0].as_str().unwrap();
+        assert!(email.starts_with("user-") && email.contains('@'));
+
+        let data_dir = scrubbed["data_dir"].as_str().unwrap();
+        assert!(data_dir.starts_with("/path-") && data_dir.ends_with(".db"));
+        assert_eq!(data_dir.matches('/').count(), 4);
+        assert!(scrubbed["windows"]
+            .as_str()
+            .unwrap()
+            .starts_with("C:\\path-"));
+
+        assert!(scrubbed["ticket"]
+            .as_str()
+            .unwrap()
+            .starts_with("see ticket-"));
+
+        // whole files keep their format
+        let src = output_path("anonymize.yml");
+        let dst = output_path("anonymize.shared.yml");
+        fs::write(&src, "host: db.example.com\nports: [80, 443]\ntoken: abc\n").unwrap();
+
+        let (scrubbed, map) =
+            files::anonymize_file(&src, &dst, &ScrubRules::default()).expect("Unable to anonymize");
+        let shared =
+            yaml_rust::YamlLoader::load_from_str(&fs::read_to_string(&dst).unwrap()).unwrap();
+        assert_eq!(shared[0]["host"].as_str(), map.get("db.example.com"));
+        assert_eq!(shared[0]["ports"][1].as_i64(), Some(443));
+        assert_eq!(scrubbed["host"].as_str(), map.get("db.example.com"));
+
+        assert!(matches!(
+            files::anonymize_file(INI_PATH, &dst, &ScrubRules::default()),
+            Err(ConfigError::UnsupportedExtension { .. })
+        ));
+
+        // extensions are matched ignoring case
+        let upper = output_path("ANONYMIZE.YML");
+        fs::copy(&src, &upper).unwrap();
+        files::anonymize_file(&upper, &dst, &ScrubRules::default()).expect("Unable to anonymize");
+
+        // keys JSON can't hold aren't merged into one
+        for data in ["[a]: 1\n[b]: 2\n", "1: a\n\"1\": b\n"] {
+            fs::write(&src, data).unwrap();
+            let err = files::anonymize_file(&src, &dst, &ScrubRules::default()).unwrap_err();
+            assert!(
+                matches!(&err, ConfigError::Parse { format: "YAML", path, .. } if *path == src),
+                "{}",
+                err
+            );
+        }
+    }
+
+    #[test]
+    fn config_error_test() {
+        use std::path::Path;
+
+        let path = output_path("missing.yml");
+        let _ = fs::remove_file(&path);
+
+        let err = files::try_load_from_yaml::<TestConfig>(&path).unwrap_err();
+        assert!(
+            matches!(&err, ConfigError::Io { source, .. } if source.kind() == io::ErrorKind::NotFound)
+        );
+        assert_eq!(err.path(), Path::new(&path));
+        assert!(err.to_string().contains(&path));
+
+        let path = output_path("broken.yml");
+        fs::write(&path, "test: [true\n").unwrap();
+        let err = files::try_load_from_file::<TestConfig>(&path).unwrap_err();
+        assert!(matches!(err, ConfigError::YamlParse { .. }));
+        assert!(err.to_string().contains(&path));
+
+        let path = output_path("broken.json");
+        fs::write(&path, "{\"test\": tru").unwrap();
+        let err = files::try_load_from_json::<TestConfig>(&path).unwrap_err();
+        assert!(matches!(err, ConfigError::JsonParse { .. }));
+        assert!(std::error::Error::source(&err).is_some());
+
+        let path = output_path("broken.toml");
+        fs::write(&path, "test = ").unwrap();
+        let err = files::try_load_from_file::<TestConfig>(&path).unwrap_err();
+        assert!(matches!(err, ConfigError::Parse { format: "TOML", .. }));
+
+        let err = files::try_load_from_file::<TestConfig>("testing/test.conf").unwrap_err();
+        assert!(
+            matches!(&err, ConfigError::UnsupportedExtension { extension, .. } if extension == "conf")
+        );
+
+        // SharedConfig doesn't opt into TOML
+        let err = files::try_load_from_file::<SharedConfig>(TOML_PATH)
+            .err()
+            .unwrap();
+        assert!(matches!(
+            err,
+            ConfigError::UnsupportedFormat { format: "TOML", .. }
+        ));
+        assert_eq!(err.path(), Path::new(TOML_PATH));
+
+        let config: TestConfig =
+            files::try_load_from_file(YAML_PATH).expect("Unable to load from file");
+        assert!(config.test);
+    }
+
+    #[test]
+    #[should_panic(expected = "rsconfig_missing_panic.yml")]
+    fn config_error_panic_test() {
+        // the panicking loaders still panic, but name the file
+        let _: TestConfig = files::load_from_yaml(output_path("missing_panic.yml"));
+    }
+
+    #[test]
+    fn load_from_file_extension_test() {
+        use std::path::Path;
+
+        let no_extension = |path: &str| {
+            let err = files::load_from_file::<TestConfig>(path).unwrap_err();
+            assert!(
+                matches!(&err, ConfigError::NoExtension { supported, .. } if supported.contains(&"yaml")),
+                "{}",
+                err
+            );
+            assert_eq!(err.path(), Path::new(path));
+        };
+
+        no_extension("testing/file");
+        no_extension("testing/file.");
+        no_extension("testing/.hidden");
+        // dots in directory names aren't extensions
+        no_extension("testing.d/file");
+
+        let err = files::load_from_file::<TestConfig>("testing/file.CONF").unwrap_err();
+        match &err {
+            ConfigError::UnsupportedExtension {
+                path,
+                extension,
+                supported,
+            } => {
+                assert_eq!(path, Path::new("testing/file.CONF"));
+                assert_eq!(extension, "CONF");
+                assert!(supported.contains(&"toml") && supported.contains(&"env"));
+            }
+            _ => panic!("expected an unsupported extension, got {}", err),
+        }
+        assert!(err.to_string().contains("`.CONF`"));
+        assert!(err.to_string().contains("yaml, yml, json"));
+
+        // underlying failures keep their own variants
+        let err = files::load_from_file::<TestConfig>("testing/missing.yml").unwrap_err();
+        assert!(matches!(err, ConfigError::Io { .. }));
+
+        // relative paths starting with a dot still find the extension
+        let config: TestConfig =
+            files::load_from_file("./testing/test.yml").expect("Unable to load from file");
+        assert!(config.test);
+        let config: TestConfig =
+            files::load_from_file(DOTENV_PATH).expect("Unable to load from file");
+        assert!(config.test);
+    }
+
+    #[test]
+    fn path_inputs_test() {
+        use std::path::{Path, PathBuf};
+
+        let yaml = PathBuf::from(YAML_PATH);
+        let config: TestConfig = files::load_from_yaml(&yaml);
+        assert!(config.test);
+        let config: TestConfig = files::load_from_json(Path::new(JSON_PATH));
+        assert!(config.test);
+        let config: TestConfig = files::load_from_file(yaml).expect("Unable to load from file");
+        assert!(config.test);
+
+        let path = PathBuf::from(output_path("path_inputs.yml"));
+        config.save_yaml_path(&path).expect("Unable to save yaml");
+        let config: TestConfig = files::load_from_file(&path).expect("Unable to load from file");
+        assert!(config.test);
+
+        let path = PathBuf::from(output_path("path_inputs.json"));
+        config.save_json_path(&path).expect("Unable to save json");
+        let config: TestConfig = files::load_from_file(&path).expect("Unable to load from file");
+        assert!(config.test);
+
+        // the dot belongs to the directory, not the file
+        let err = files::load_from_file::<TestConfig>(Path::new("./my.app/config")).unwrap_err();
+        assert!(matches!(err, ConfigError::NoExtension { .. }), "{}", err);
+    }
+
+    #[test]
+    fn extension_case_test() {
+        let load = |fixture: &str, file_name: &str| {
+            let path = output_path(file_name);
+            fs::copy(fixture, &path).unwrap();
+
+            let config: TestConfig = files::load_from_file(&path)
+                .unwrap_or_else(|e| panic!("Unable to load {:?}: {}", file_name, e));
+            assert!(config.test);
+        };
+
+        load(YAML_PATH, "CONFIG.YML");
+        load(YAML_PATH, "Config.Yaml");
+        load(JSON_PATH, "settings.Json");
+        load(YAML_PATH, "配置.yml");
+        load(JSON_PATH, "paramètres.JSON");
+        load(YAML_PATH, "конфиг.YmL");
+        load(TOML_PATH, "Cargo.TOML");
+        // windows doesn't allow trailing spaces in file names
+        #[cfg(not(windows))]
+        load(YAML_PATH, "padded.yml ");
+
+        let dir = env::temp_dir().join("rsconfig_extension_case");
+        fs::create_dir_all(&dir).unwrap();
+        fs::copy(DOTENV_PATH, dir.join(".ENV")).unwrap();
+        let config: TestConfig =
+            files::load_from_file(dir.join(".ENV")).expect("Unable to load from file");
+        assert!(config.test);
+
+        // only whitespace after the dot is still no extension
+        let err = files::load_from_file::<TestConfig>("testing/file. ").unwrap_err();
+        assert!(matches!(err, ConfigError::NoExtension { .. }), "{}", err);
+    }
+
+    #[test]
+    fn detect_format_test() {
+        use files::ConfigFormat;
+
+        assert_eq!(
+            files::detect_format(&fs::read_to_string(JSON_PATH).unwrap()),
+            Some(ConfigFormat::Json)
+        );
+        assert_eq!(
+            files::detect_format(&fs::read_to_string(YAML_PATH).unwrap()),
+            Some(ConfigFormat::Yaml)
+        );
+        assert_eq!(files::detect_format("\n  [1, 2]"), Some(ConfigFormat::Json));
+        // flow style YAML that isn't JSON
+        assert_eq!(
+            files::detect_format("{test: true}"),
+            Some(ConfigFormat::Yaml)
+        );
+        assert_eq!(files::detect_format("- a\n- b"), Some(ConfigFormat::Yaml));
+        assert_eq!(files::detect_format("plain text"), None);
+        assert_eq!(files::detect_format(""), None);
+        assert_eq!(files::detect_format("{\"test\": tru"), None);
+
+        let load = |file_name: &str, data: &str| {
+            let path = output_path(file_name);
+            fs::write(&path, data).unwrap();
+            files::load_with_detection::<TestConfig>(&path)
+        };
+
+        assert!(
+            load("appconfig", "{\"test\": true}")
+                .expect("Unable to load json")
+                .test
+        );
+        assert!(
+            load("appconfig_yaml", "test: true")
+                .expect("Unable to load yaml")
+                .test
+        );
+        // unrecognized extensions are sniffed too
+        assert!(
+            load("appconfig.prod", "test: true")
+                .expect("Unable to load yaml")
+                .test
+        );
+        // recognized extensions aren't second-guessed
+        assert!(matches!(
+            load("detect.json", "test: true"),
+            Err(ConfigError::JsonParse { .. })
+        ));
+
+        let err = load("appconfig_broken", "{\"test\": tru").unwrap_err();
+        match &err {
+            ConfigError::UnknownFormat { failures, .. } => {
+                let formats: Vec<_> = failures.iter().map(|(format, _)| *format).collect();
+                assert_eq!(formats, ["JSON", "YAML"]);
+            }
+            _ => panic!("expected an unknown format, got {}", err),
+        }
+        assert!(err.to_string().contains("not JSON"), "{}", err);
+
+        let err =
+            files::load_with_detection::<TestConfig>(output_path("appconfig_missing")).unwrap_err();
+        assert!(matches!(err, ConfigError::Io { .. }), "{}", err);
+    }
+
+    #[test]
+    fn save_to_file_test() {
+        for (fixture, file_name) in [
+            (YAML_PATH, "save_to_file.yml"),
+            (JSON_PATH, "save_to_file.json"),
+            (YAML_PATH, "save_to_file.JSON"),
+        ] {
+            let config: TestConfig =
+                files::load_from_file(fixture).expect("Unable to load from file");
+            let path = output_path(file_name);
+            files::save_to_file(&config, &path).expect("Unable to save to file");
+
+            let saved: TestConfig =
+                files::load_from_file(&path).expect("Unable to load saved file");
+            assert_eq!(saved.test, config.test);
+        }
+
+        // the file is JSON, not YAML, even though the config came from YAML
+        let data = fs::read_to_string(output_path("save_to_file.JSON")).unwrap();
+        assert_eq!(files::detect_format(&data), Some(files::ConfigFormat::Json));
+
+        let config = TestConfig { test: true };
+        let err = files::save_to_file(&config, output_path("save_to_file.toml")).unwrap_err();
+        assert!(
+            matches!(err, ConfigError::UnsupportedFormat { format: "TOML", .. }),
+            "{}",
+            err
+        );
+        let err = files::save_to_file(&config, output_path("save_to_file.conf")).unwrap_err();
+        assert!(
+            matches!(&err, ConfigError::UnsupportedExtension { extension, .. } if extension == "conf"),
+            "{}",
+            err
+        );
+        let err = files::save_to_file(&config, output_path("save_to_file")).unwrap_err();
+        assert!(matches!(err, ConfigError::NoExtension { .. }), "{}", err);
+    }
+
+    #[test]
+    fn reader_test() {
+        use std::{fs::File, io::Read};
+
+        let config: TestConfig = files::load_from_yaml_reader(File::open(YAML_PATH).unwrap())
+            .expect("Unable to load yaml");
+        assert!(config.test);
+        let config: TestConfig = files::load_from_json_reader(File::open(JSON_PATH).unwrap())
+            .expect("Unable to load json");
+        assert!(config.test);
+        let config: TestConfig =
+            files::load_from_json_reader(&b"{\"test\": true}\n"[..]).expect("Unable to load json");
+        assert!(config.test);
+
+        let err = |result: std::result::Result<TestConfig, ConfigError>| result.err().unwrap();
+        let kind = |e: ConfigError| match e {
+            ConfigError::Io { path, source } => {
+                assert!(path.as_os_str().is_empty());
+                source.kind()
+            }
+            e => panic!("expected an io error, got {}", e),
+        };
+
+        let e = err(files::load_from_yaml_reader(&b"test: [true"[..]));
+        assert!(matches!(e, ConfigError::YamlParse { .. }), "{}", e);
+        assert!(e.location().is_some());
+        assert_eq!(
+            kind(err(files::load_from_yaml_reader(&b"test: \xff"[..]))),
+            io::ErrorKind::InvalidData
+        );
+        for data in [
+            &b"{\"test\": tru}"[..],
+            b"{\"test\": true} {}",
+            b"{\"test\": ",
+        ] {
+            let e = err(files::load_from_json_reader(data));
+            assert!(matches!(e, ConfigError::JsonParse { .. }), "{}", e);
+            assert!(e.location().is_some());
+        }
+
+        // errors from the reader itself keep their kind
+        struct Broken;
+        impl Read for Broken {
+            fn read(&mut self, _buf: &mut [u8]) -> Result<usize> {
+                Err(io::Error::new(
+                    io::ErrorKind::ConnectionReset,
+                    "connection reset",
+                ))
+            }
+        }
+        assert_eq!(
+            kind(err(files::load_from_yaml_reader(Broken))),
+            io::ErrorKind::ConnectionReset
+        );
+        assert_eq!(
+            kind(err(files::load_from_json_reader(Broken))),
+            io::ErrorKind::ConnectionReset
+        );
+    }
+
+    #[test]
+    fn writer_test() {
+        let config = TestConfig { test: true };
+
+        let mut data = Vec::new();
+        config.write_yaml(&mut data).expect("Unable to write yaml");
+        let written: TestConfig =
+            files::load_from_yaml_reader(data.as_slice()).expect("Unable to load yaml");
+        assert!(written.test);
+
+        let mut data = Vec::new();
+        config.write_json(&mut data).expect("Unable to write json");
+        let written: TestConfig =
+            files::load_from_json_reader(data.as_slice()).expect("Unable to load json");
+        assert!(written.test);
+
+        // SharedConfig only saves to files
+        let shared = SharedConfig { test: true };
+        assert_eq!(
+            shared.write_yaml(Vec::new()).unwrap_err().kind(),
+            io::ErrorKind::Unsupported
+        );
+        assert_eq!(
+            shared.write_json(Vec::new()).unwrap_err().kind(),
+            io::ErrorKind::Unsupported
+        );
+    }
+
+    #[test]
+    fn str_loaders_test() {
+        let config: TestConfig =
+            files::load_from_yaml_str("test: true").expect("Unable to load yaml");
+        assert!(config.test);
+        let config: TestConfig =
+            files::load_from_json_str("{\"test\": true}").expect("Unable to load json");
+        assert!(config.test);
+
+        let err = files::load_from_yaml_str::<TestConfig>("a: 1\ntest: @true\nb: 2")
+            .err()
+            .unwrap();
+        assert!(matches!(err, ConfigError::YamlParse { .. }));
+        assert_eq!(err.path(), Path::new(""));
+        assert_eq!(err.location().map(|(line, _)| line), Some(2));
+        assert!(
+            err.to_string().starts_with("Failed to parse YAML: "),
+            "{}",
+            err
+        );
+        assert!(err.to_string().contains("line 2"), "{}", err);
+
+        let err = files::load_from_json_str::<TestConfig>("{\n  \"test\": nul,\n}")
+            .err()
+            .unwrap();
+        assert!(matches!(err, ConfigError::JsonParse { .. }));
+        assert_eq!(err.location(), Some((2, 14)));
+        assert!(err.to_string().contains("line 2 column 14"), "{}", err);
+
+        // errors from files still name the file and have a location
+        let path = output_path("str_loaders.json");
+        fs::write(&path, "{\n  \"test\": nul,\n}").unwrap();
+        let err = files::try_load_from_json::<TestConfig>(&path).unwrap_err();
+        assert_eq!(err.path(), Path::new(&path));
+        assert_eq!(err.location(), Some((2, 14)));
+    }
+
+    #[test]
+    fn bom_test() {
+        // UTF-8 with a byte order mark and CRLF line endings
+        let config: TestConfig = files::load_from_json("testing/bom.json");
+        assert!(config.test);
+        // UTF-16LE with a byte order mark
+        let config: TestConfig = files::load_from_yaml("testing/utf16.yml");
+        assert!(config.test);
+        let config: TestConfig =
+            files::load_from_file("testing/utf16.yml").expect("Unable to load from file");
+        assert!(config.test);
+
+        let config: TestConfig =
+            files::load_from_json_bytes(&fs::read("testing/bom.json").unwrap())
+                .expect("Unable to load json");
+        assert!(config.test);
+        let utf16be: Vec<u8> = [0xFE, 0xFF]
+            .into_iter()
+            .chain("test: true".encode_utf16().flat_map(u16::to_be_bytes))
+            .collect();
+        let config: TestConfig =
+            files::load_from_yaml_bytes(&utf16be).expect("Unable to load yaml");
+        assert!(config.test);
+
+        let invalid = |result: std::result::Result<TestConfig, ConfigError>| match result {
+            Err(ConfigError::Io { source, .. }) => source.kind() == io::ErrorKind::InvalidData,
+            _ => false,
+        };
+        assert!(invalid(files::load_from_yaml_bytes(b"test: \xff")));
+        // odd length
+        assert!(invalid(files::load_from_yaml_bytes(b"\xff\xfet\x00e")));
+        // unpaired surrogate
+        assert!(invalid(files::load_from_json_bytes(b"\xff\xfe\x00\xd8")));
+    }
+
+    #[test]
+    fn save_create_dirs_test() {
+        use files::SaveOptions;
+
+        let config = TestConfig { test: true };
+        let dir = env::temp_dir().join(format!("rsconfig_create_dirs_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+
+        // two levels of missing directories
+        let path = dir.join("a").join("b").join("config.yml");
+        files::save_to_file(&config, &path).expect("Unable to save to file");
+        let saved: TestConfig = files::load_from_file(&path).expect("Unable to load saved file");
+        assert!(saved.test);
+
+        let path = dir.join("c").join("config.json");
+        let err = files::save_to_file_with(&config, &path, SaveOptions::new().create_dirs(false))
+            .unwrap_err();
+        match &err {
+            ConfigError::Write { source, .. } => assert_eq!(source.kind(), io::ErrorKind::NotFound),
+            _ => panic!("expected a write error, got {}", err),
+        }
+        assert_eq!(err.path(), path);
+        assert!(!dir.join("c").exists());
+
+        // the parent is a file
+        let path = dir
+            .join("a")
+            .join("b")
+            .join("config.yml")
+            .join("config.json");
+        let err = files::save_to_file(&config, &path).unwrap_err();
+        assert!(matches!(err, ConfigError::Write { .. }), "{}", err);
+        assert!(err.to_string().starts_with("Failed to write"), "{}", err);
+
+        // no directories are created for formats that can't be saved
+        let err = files::save_to_file(&config, dir.join("d").join("config.toml")).unwrap_err();
+        assert!(
+            matches!(err, ConfigError::UnsupportedFormat { .. }),
+            "{}",
+            err
+        );
+        assert!(!dir.join("d").exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn save_with_backup_test() {
+        use files::BackupPolicy;
+
+        let dir = env::temp_dir().join(format!("rsconfig_backup_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+
+        // nothing to back up yet
+        let path = dir.join("config.yml");
+        files::save_with_backup(&TestConfig { test: true }, &path, BackupPolicy::default())
+            .expect("Unable to save");
+        assert!(!dir.join("config.yml.bak").exists());
+
+        files::save_with_backup(&TestConfig { test: false }, &path, BackupPolicy::default())
+            .expect("Unable to save");
+        let backup: TestConfig = files::load_from_yaml(dir.join("config.yml.bak"));
+        assert!(backup.test);
+        let saved: TestConfig = files::load_from_yaml(&path);
+        assert!(!saved.test);
+
+        let path = dir.join("custom.json");
+        fs::write(&path, "{\"test\": true}").unwrap();
+        files::save_with_backup(
+            &TestConfig { test: false },
+            &path,
+            BackupPolicy::Suffix("~".to_string()),
+        )
+        .expect("Unable to save");
+        assert_eq!(
+            fs::read_to_string(dir.join("custom.json~")).unwrap(),
+            "{\"test\": true}"
+        );
+
+        // each save pushes the older backups back, dropping the oldest
+        let path = dir.join("rotated.yml");
+        for n in 0..4 {
+            fs::write(&path, format!("test: true\nsave: {}", n)).unwrap();
+            files::save_with_backup(&TestConfig { test: false }, &path, BackupPolicy::Rotate(2))
+                .expect("Unable to save");
+        }
+        assert_eq!(
+            fs::read_to_string(dir.join("rotated.yml.bak.1")).unwrap(),
+            "test: true\nsave: 3"
+        );
+        assert_eq!(
+            fs::read_to_string(dir.join("rotated.yml.bak.2")).unwrap(),
+            "test: true\nsave: 2"
+        );
+        assert!(!dir.join("rotated.yml.bak.3").exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    #[cfg(feature = "locking")]
+    fn locking_test() {
+        use files::LockMode;
+
+        let path = output_path("locking.yml");
+        files::save_to_file_locked(&TestConfig { test: true }, &path, LockMode::Block)
+            .expect("Unable to save");
+        let config: TestConfig =
+            files::load_from_file_locked(&path, LockMode::Try).expect("Unable to load");
+        assert!(config.test);
+
+        // another instance saving the config
+        let held = fs::File::open(format!("{}.lock", path)).unwrap();
+        held.lock().unwrap();
+
+        let err = files::load_from_file_locked::<TestConfig>(&path, LockMode::Try).unwrap_err();
+        assert!(matches!(err, ConfigError::Locked { .. }), "{}", err);
+        assert_eq!(err.path(), Path::new(&path));
+        let err = files::save_to_file_locked(&TestConfig { test: false }, &path, LockMode::Try)
+            .unwrap_err();
+        assert!(matches!(err, ConfigError::Locked { .. }), "{}", err);
+        assert_eq!(io::Error::from(err).kind(), io::ErrorKind::WouldBlock);
+
+        // readers share the lock
+        held.unlock().unwrap();
+        held.lock_shared().unwrap();
+        let config: TestConfig =
+            files::load_from_file_locked(&path, LockMode::Try).expect("Unable to load");
+        assert!(config.test);
+        assert!(
+            files::save_to_file_locked(&TestConfig { test: false }, &path, LockMode::Try).is_err()
+        );
+
+        // a blocked save goes through once the lock is released
+        let saver = {
+            let path = path.clone();
+            thread::spawn(move || {
+                files::save_to_file_locked(&TestConfig { test: false }, &path, LockMode::Block)
+            })
+        };
+        thread::sleep(Duration::from_millis(50));
+        let config: TestConfig = files::load_from_yaml(&path);
+        assert!(config.test);
+        drop(held);
+        saver.join().unwrap().expect("Unable to save");
+        let config: TestConfig =
+            files::load_from_file_locked(&path, LockMode::Block).expect("Unable to load");
+        assert!(!config.test);
+    }
+
+    #[test]
+    fn load_or_default_test() {
+        // TestConfig defaults to false, the fixtures are true
+        let config: TestConfig =
+            files::load_or_default(output_path("missing_default.yml")).expect("Unable to load");
+        assert!(!config.test);
+        let config: TestConfig = files::load_yaml_or_default(output_path("missing_default.yml"))
+            .expect("Unable to load");
+        assert!(!config.test);
+        let config: TestConfig = files::load_json_or_default(output_path("missing_default.json"))
+            .expect("Unable to load");
+        assert!(!config.test);
+
+        let config: TestConfig = files::load_or_default(YAML_PATH).expect("Unable to load");
+        assert!(config.test);
+        let config: TestConfig = files::load_json_or_default(JSON_PATH).expect("Unable to load");
+        assert!(config.test);
+
+        for contents in ["", "\n  \n"] {
+            let path = output_path("empty_default.yml");
+            fs::write(&path, contents).unwrap();
+            let err = files::load_or_default::<TestConfig>(&path).unwrap_err();
+            assert!(matches!(err, ConfigError::Parse { .. }), "{}", err);
+            assert!(err.to_string().contains("empty"), "{}", err);
+            assert!(files::load_yaml_or_default::<TestConfig>(&path).is_err());
+        }
+
+        let path = output_path("invalid_default.json");
+        fs::write(&path, "{\"test\": tru").unwrap();
+        let err = files::load_or_default::<TestConfig>(&path).unwrap_err();
+        assert!(matches!(err, ConfigError::JsonParse { .. }), "{}", err);
+        let path = output_path("invalid_default.yml");
+        fs::write(&path, "test: [true").unwrap();
+        let err = files::load_yaml_or_default::<TestConfig>(&path).unwrap_err();
+        assert!(matches!(err, ConfigError::YamlParse { .. }), "{}", err);
+    }
+
+    #[test]
+    fn load_or_create_test() {
+        let dir = env::temp_dir().join(format!("rsconfig_create_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+
+        let path = dir.join("nested").join("config.yml");
+        let config: TestConfig = files::load_or_create(&path).expect("Unable to create");
+        assert!(!config.test);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "test: false");
+        // only the config is left behind
+        assert_eq!(fs::read_dir(dir.join("nested")).unwrap().count(), 1);
+
+        // existing files are loaded, not replaced
+        fs::write(&path, "test: true").unwrap();
+        let config: TestConfig = files::load_or_create(&path).expect("Unable to load");
+        assert!(config.test);
+
+        let path = dir.join("config.json");
+        fs::write(&path, "{\"test\": tru").unwrap();
+        let err = files::load_or_create::<TestConfig>(&path).unwrap_err();
+        assert!(matches!(err, ConfigError::JsonParse { .. }), "{}", err);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "{\"test\": tru");
+
+        let err = files::load_or_create::<TestConfig>(dir.join("config.toml")).unwrap_err();
+        assert!(
+            matches!(err, ConfigError::UnsupportedFormat { .. }),
+            "{}",
+            err
+        );
+        assert!(!dir.join("config.toml").exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn load_from_first_test() {
+        use std::path::PathBuf;
+
+        let missing = output_path("first_missing.yml");
+        let (config, path) =
+            files::load_from_first::<TestConfig>(&[missing.as_str(), YAML_PATH, JSON_PATH])
+                .expect("Unable to load");
+        assert!(config.test);
+        assert_eq!(path, PathBuf::from(YAML_PATH));
+
+        // a broken file stops the chain
+        let broken = output_path("first_broken.json");
+        fs::write(&broken, "{\"test\": tru").unwrap();
+        let err =
+            files::load_from_first::<TestConfig>(&[missing.as_str(), broken.as_str(), YAML_PATH])
+                .unwrap_err();
+        assert!(matches!(err, ConfigError::JsonParse { .. }), "{}", err);
+        assert_eq!(err.path(), Path::new(&broken));
+
+        let other = output_path("first_other.json");
+        let err =
+            files::load_from_first::<TestConfig>(&[PathBuf::from(&missing), PathBuf::from(&other)])
+                .unwrap_err();
+        match &err {
+            ConfigError::NoneFound { paths } => {
+                assert_eq!(paths, &[PathBuf::from(&missing), PathBuf::from(&other)])
+            }
+            _ => panic!("expected nothing to be found, got {}", err),
+        }
+        assert_eq!(err.path(), Path::new(&missing));
+        assert!(err.to_string().contains(&other), "{}", err);
+        assert_eq!(io::Error::from(err).kind(), io::ErrorKind::NotFound);
+
+        let err = files::load_from_first::<TestConfig>(&[] as &[&str]).unwrap_err();
+        assert!(matches!(&err, ConfigError::NoneFound { paths } if paths.is_empty()));
+    }
+
+    #[test]
+    fn config_path_test() {
+        use std::{ffi::OsString, path::PathBuf};
+
+        if let Some(path) = files::config_path("myapp", "config.yml") {
+            assert!(path.is_absolute());
+            assert!(path.ends_with("myapp/config.yml"));
+        }
+        if let Some(path) = files::system_config_path("myapp", "config.yml") {
+            assert!(path.ends_with("myapp/config.yml"));
+        }
+
+        let lookup = |vars: &'static [(&'static str, &'static str)]| {
+            move |name: &str| {
+                vars.iter()
+                    .find(|(key, _)| *key == name)
+                    .map(|(_, value)| OsString::from(value))
+            }
+        };
+
+        #[cfg(all(unix, not(target_os = "macos")))]
+        {
+            let xdg = lookup(&[("XDG_CONFIG_HOME", "/xdg"), ("HOME", "/home/me")]);
+            assert_eq!(files::user_config_dir(&xdg), Some(PathBuf::from("/xdg")));
+            let home = lookup(&[("HOME", "/home/me")]);
+            assert_eq!(
+                files::user_config_dir(&home),
+                Some(PathBuf::from("/home/me/.config"))
+            );
+            // relative values are ignored
+            let relative = lookup(&[("XDG_CONFIG_HOME", "config"), ("HOME", "/home/me")]);
+            assert_eq!(
+                files::user_config_dir(&relative),
+                Some(PathBuf::from("/home/me/.config"))
+            );
+            assert_eq!(files::system_config_dir(&home), Some(PathBuf::from("/etc")));
+        }
+        #[cfg(windows)]
+        {
+            let vars = lookup(&[
+                ("APPDATA", "C:\\Users\\me\\AppData\\Roaming"),
+                ("ProgramData", "C:\\ProgramData"),
+            ]);
+            assert_eq!(
+                files::user_config_dir(&vars),
+                Some(PathBuf::from("C:\\Users\\me\\AppData\\Roaming"))
+            );
+            assert_eq!(
+                files::system_config_dir(&vars),
+                Some(PathBuf::from("C:\\ProgramData"))
+            );
+        }
+
+        assert_eq!(files::user_config_dir(&lookup(&[])), None);
+    }
+
+    #[test]
+    fn env_prefixed_test() {
+        use serde_json::json;
+
+        let load = |vars: &[(&str, &str)]| {
+            crate::env::prefixed_value_from(
+                vars.iter().map(|(k, v)| (k.into(), v.into())),
+                "MYAPP_",
+            )
+        };
+
+        let value = load(&[
+            ("MYAPP_TEST", "true"),
+            ("MYAPP_LOG_LEVEL", "debug"),
+            ("MYAPP_DB__HOST", "localhost"),
+            ("MYAPP_DB__PORT", "5432"),
+            ("MYAPP_DB__POOL__MAX", "-1"),
+            ("MYAPP_PORTS", "80, 443,true"),
+            ("MYAPP_BIG", "18446744073709551615"),
+            ("MYAPP_VERSION", "1.5"),
+            ("OTHER_TEST", "false"),
+        ])
+        .expect("Unable to load");
+
+        assert_eq!(
+            value,
+            json!({
+                "test": true,
+                "log_level": "debug",
+                "db": {"host": "localhost", "port": 5432, "pool": {"max": -1}},
+                "ports": [80, 443, true],
+                "big": 18446744073709551615u64,
+                "version": "1.5",
+            })
+        );
+
+        let config: TestConfig = TestConfig::from_json(load(&[("MYAPP_TEST", "true")]).unwrap());
+        assert!(config.test);
+
+        let variable = |vars: &[(&str, &str)]| load(vars).unwrap_err().variable;
+        assert_eq!(
+            variable(&[("MYAPP_DB", "x"), ("MYAPP_DB__HOST", "y")]),
+            "MYAPP_DB__HOST"
+        );
+        assert_eq!(
+            variable(&[("MYAPP_DB__HOST", "y"), ("MYAPP_DB__HOST__NAME", "z")]),
+            "MYAPP_DB__HOST__NAME"
+        );
+        assert_eq!(variable(&[("MYAPP_DB____HOST", "y")]), "MYAPP_DB____HOST");
+        assert_eq!(variable(&[("MYAPP_", "y")]), "MYAPP_");
+        assert_eq!(
+            variable(&[("MYAPP_BIG", "99999999999999999999")]),
+            "MYAPP_BIG"
+        );
+        assert!(load(&[("MYAPP_BIG", "99999999999999999999")])
+            .unwrap_err()
+            .to_string()
+            .contains("too large"));
+
+        #[cfg(unix)]
+        {
+            use std::{ffi::OsString, os::unix::ffi::OsStringExt};
+
+            let vars = [(OsString::from("MYAPP_BAD"), OsString::from_vec(vec![0xff]))];
+            assert_eq!(
+                crate::env::prefixed_value_from(vars, "MYAPP_")
+                    .unwrap_err()
+                    .variable,
+                "MYAPP_BAD"
+            );
+        }
+    }
+
+    #[test]
+    fn expand_env_test() {
+        use files::LoadOptions;
+
+        let lookup = |name: &str| match name {
+            "HOST" => Some("db.local".to_string()),
+            "EMPTY" => Some(String::new()),
+            _ => None,
+        };
+        let expand = |data: &str| files::expand_vars(data, Path::new("config.yml"), &lookup);
+
+        assert_eq!(
+            expand("url: ${HOST}:${PORT:-5432}").unwrap(),
+            "url: db.local:5432"
+        );
+        assert_eq!(
+            expand("a: ${EMPTY:-fallback} ${EMPTY}.").unwrap(),
+            "a: fallback ."
+        );
+        assert_eq!(
+            expand("price: $$5 and $HOST, $").unwrap(),
+            "price: $5 and $HOST, $"
+        );
+        assert_eq!(expand("$${HOST}").unwrap(), "${HOST}");
+        assert_eq!(expand("${MISSING:-}").unwrap(), "");
+
+        let err = expand("url: ${DATABASE_URL}").unwrap_err();
+        assert!(
+            matches!(&err, ConfigError::UnsetVariable { variable, .. } if variable == "DATABASE_URL")
+        );
+        assert_eq!(err.path(), Path::new("config.yml"));
+        assert!(
+            err.to_string().contains("DATABASE_URL") && err.to_string().contains("config.yml"),
+            "{}",
+            err
+        );
+
+        assert!(matches!(
+            expand("a: ${HOST"),
+            Err(ConfigError::Parse { .. })
+        ));
+        assert!(matches!(
+            expand("a: ${1HOST}"),
+            Err(ConfigError::Parse { .. })
+        ));
+        assert!(matches!(expand("a: ${}"), Err(ConfigError::Parse { .. })));
+
+        let dir = env::temp_dir().join(format!("rsconfig_expand_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        let path = dir.join("config.yml");
+        fs::write(&path, "test: ${RSCONFIG_UNSET_TEST_VAR:-true}").unwrap();
+
+        let config: TestConfig =
+            files::load_from_file_with(&path, LoadOptions::new().expand_env(true))
+                .expect("Unable to load");
+        assert!(config.test);
+
+        let path = dir.join("config.json");
+        fs::write(&path, "{\"test\": ${RSCONFIG_UNSET_TEST_VAR}}").unwrap();
+
+        let err =
+            files::load_from_file_with::<TestConfig>(&path, LoadOptions::new().expand_env(true))
+                .unwrap_err();
+        assert!(
+            matches!(&err, ConfigError::UnsetVariable { variable, .. } if variable == "RSCONFIG_UNSET_TEST_VAR")
+        );
+        assert_eq!(err.path(), path);
+
+        // placeholders are left alone unless expansion is asked for
+        assert!(matches!(
+            files::load_from_file::<TestConfig>(&path),
+            Err(ConfigError::JsonParse { .. })
+        ));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn layers_test() {
+        use layers::{ConfigBuilder, Layer, LayerError};
+        use serde_json::json;
+
+        let dir = env::temp_dir().join(format!("rsconfig_layers_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        let yaml = dir.join("base.yml");
+        fs::write(
+            &yaml,
+            "test: true\ndb:\n  host: localhost\n  port: 5432\nname: base",
+        )
+        .unwrap();
+        let toml = dir.join("override.toml");
+        fs::write(&toml, "name = \"override\"\n[db]\nport = 6432\n").unwrap();
+
+        let args = [
+            "prog",
+            "input.txt",
+            "--db.pool-size=4",
+            "--verbose",
+            "--name=cli",
+            "--name=last",
+            "--",
+            "--ignored",
+        ];
+        let merged = ConfigBuilder::<TestConfig>::new()
+            .defaults(TestConfig::default())
+            .file(&yaml)
+            .optional_file(dir.join("missing.json"))
+            .file(&toml)
+            .env_prefix("RSCONFIG_LAYERS_UNSET_")
+            .args(args.iter().map(|arg| arg.to_string()).collect())
+            .merge()
+            .expect("Unable to merge");
+
+        assert_eq!(
+            merged.value,
+            json!({
+                "test": true,
+                "db": {"host": "localhost", "port": 6432, "pool_size": 4},
+                "name": "last",
+                "verbose": true,
+            })
+        );
+        assert_eq!(merged.sources["test"], Layer::File(yaml.clone()));
+        assert_eq!(merged.sources["db"], Layer::Args);
+        assert_eq!(merged.sources["name"], Layer::Args);
+        assert_eq!(merged.sources.len(), 4);
+
+        let config = ConfigBuilder::<TestConfig>::new()
+            .defaults(TestConfig::default())
+            .args(vec!["--test".to_string()])
+            .build()
+            .expect("Unable to build");
+        assert!(config.test);
+
+        let merged = ConfigBuilder::<TestConfig>::new()
+            .defaults(TestConfig::default())
+            .merge()
+            .unwrap();
+        assert_eq!(merged.sources["test"].to_string(), "defaults");
+
+        // types that only implement save_json still work as defaults
+        struct SaveOnly {
+            port: u16,
+        }
+
+        impl JsonConfig for SaveOnly {
+            fn from_json(val: Value) -> Self {
+                Self {
+                    port: val["port"].as_u64().unwrap() as u16,
+                }
+            }
+
+            fn save_json(&self, path: &str) -> Result<()> {
+                fs::write(path, json!({ "port": self.port }).to_string())
+            }
+        }
+
+        let config = ConfigBuilder::<SaveOnly>::new()
+            .defaults(SaveOnly { port: 8080 })
+            .merge()
+            .expect("Unable to merge save_json defaults");
+        assert_eq!(config.value, json!({ "port": 8080 }));
+        assert_eq!(config.sources["port"], Layer::Defaults);
+
+        let config = ConfigBuilder::<SaveOnly>::new()
+            .defaults(SaveOnly { port: 8080 })
+            .args(vec!["--port=9090".to_string()])
+            .build()
+            .unwrap();
+        assert_eq!(config.port, 9090);
+
+        let err = ConfigBuilder::<TestConfig>::new()
+            .file(dir.join("missing.json"))
+            .merge()
+            .unwrap_err();
+        assert!(matches!(err, LayerError::File(ConfigError::Io { .. })));
+
+        // YAML keys that can't be JSON keys are an error rather than merged together
+        let complex = dir.join("complex.yml");
+        fs::write(&complex, "test: true\n[a, b]: 1\n? {c: d}\n: 2\n").unwrap();
+        let err = ConfigBuilder::<TestConfig>::new()
+            .file(&complex)
+            .merge()
+            .unwrap_err();
+        assert!(
+            matches!(
+                &err,
+                LayerError::File(ConfigError::Parse { format: "YAML", .. })
+            ),
+            "{}",
+            err
+        );
+
+        let err = ConfigBuilder::<TestConfig>::new()
+            .args(vec!["--db=1".to_string(), "--db.port=2".to_string()])
+            .merge()
+            .unwrap_err();
+        assert!(matches!(&err, LayerError::Args { flag, .. } if flag == "--db.port=2"));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn load_merged_test() {
+        use serde_json::json;
+
+        let dir = env::temp_dir().join(format!("rsconfig_merged_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        let base = dir.join("base.yml");
+        fs::write(&base, "test: true").unwrap();
+        let user = dir.join("user.json");
+        fs::write(&user, "{\"test\": false}").unwrap();
+        let missing = dir.join("missing.yml");
+
+        let config: TestConfig =
+            files::load_merged(&[&base, &missing, &user]).expect("Unable to load");
+        assert!(!config.test);
+        let config: TestConfig =
+            files::load_merged(&[&user, &base, &missing]).expect("Unable to load");
+        assert!(config.test);
+
+        let err = files::load_merged::<TestConfig>(&[&missing]).unwrap_err();
+        assert!(
+            matches!(&err, ConfigError::NoneFound { paths } if paths == std::slice::from_ref(&missing))
+        );
+
+        fs::write(&user, "{\"test\": }").unwrap();
+        assert!(matches!(
+            files::load_merged::<TestConfig>(&[&base, &user]),
+            Err(ConfigError::JsonParse { .. })
+        ));
+
+        let mut value = json!({"db": {"host": "localhost", "port": 5432}, "tags": ["a", "b"]});
+        value.merge(json!({"db": {"port": 6432}, "tags": ["c"]}));
+        assert_eq!(
+            value,
+            json!({"db": {"host": "localhost", "port": 6432}, "tags": ["c"]})
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn partial_config_test() {
+        #[derive(Debug, PartialEq)]
+        struct Settings {
+            log_level: String,
+            verbose: bool,
+        }
+
+        #[derive(Debug, Default)]
+        struct PartialSettings {
+            log_level: Option<String>,
+            verbose: Option<bool>,
+        }
+
+        impl YamlConfig for PartialSettings {
+            fn from_yaml(yaml: Vec<yaml_rust::Yaml>) -> Self {
+                Self {
+                    log_level: yaml[0]["log_level"].as_str().map(str::to_string),
+                    verbose: yaml[0]["verbose"].as_bool(),
+                }
+            }
+
+            fn save_yaml(&self, _path: &str) -> Result<()> {
+                Ok(())
+            }
+        }
+
+        impl JsonConfig for PartialSettings {
+            fn from_json(val: serde_json::Value) -> Self {
+                Self {
+                    log_level: val["log_level"].as_str().map(str::to_string),
+                    verbose: val["verbose"].as_bool(),
+                }
+            }
+
+            fn save_json(&self, _path: &str) -> Result<()> {
+                Ok(())
+            }
+        }
+
+        impl FileConfig for PartialSettings {}
+
+        impl PartialConfig for PartialSettings {
+            type Complete = Settings;
+
+            fn apply_to(self, target: &mut Settings) {
+                if let Some(log_level) = self.log_level {
+                    target.log_level = log_level;
+                }
+                if let Some(verbose) = self.verbose {
+                    target.verbose = verbose;
+                }
+            }
+        }
+
+        let dir = env::temp_dir().join(format!("rsconfig_partial_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        let system = dir.join("system.yml");
+        fs::write(&system, "log_level: warn\nverbose: true").unwrap();
+        let user = dir.join("user.json");
+        fs::write(&user, "{\"log_level\": \"debug\"}").unwrap();
+        let empty = dir.join("empty.yml");
+        fs::write(&empty, "# nothing overridden yet\n").unwrap();
+        let missing = dir.join("missing.json");
+
+        let base = || Settings {
+            log_level: "info".to_string(),
+            verbose: false,
+        };
+
+        let mut config = base();
+        files::load_partial_from_yaml::<PartialSettings>(&system)
+            .unwrap()
+            .apply_to(&mut config);
+        files::load_partial_from_json::<PartialSettings>(&user)
+            .unwrap()
+            .apply_to(&mut config);
+        assert_eq!(
+            config,
+            Settings {
+                log_level: "debug".to_string(),
+                verbose: true
+            }
+        );
+
+        let mut config = base();
+        files::apply_partial_files::<PartialSettings>(
+            &mut config,
+            &[&user, &empty, &missing, &system],
+        )
+        .unwrap();
+        assert_eq!(
+            config,
+            Settings {
+                log_level: "warn".to_string(),
+                verbose: true
+            }
+        );
+
+        let partial: PartialSettings = files::load_partial_from_file(&empty).unwrap();
+        assert!(partial.log_level.is_none() && partial.verbose.is_none());
+        fs::write(&user, "  \n").unwrap();
+        let partial: PartialSettings = files::load_partial_from_file(&user).unwrap();
+        assert!(partial.log_level.is_none() && partial.verbose.is_none());
+
+        // nothing is applied if one of the files is broken
+        fs::write(&user, "{\"verbose\": tru}").unwrap();
+        let mut config = base();
+        let err = files::apply_partial_files::<PartialSettings>(&mut config, &[&system, &user])
+            .unwrap_err();
+        assert!(matches!(err, ConfigError::JsonParse { .. }));
+        assert_eq!(config, base());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn load_from_args_test() {
+        use std::path::PathBuf;
+
+        let dir = env::temp_dir().join(format!("rsconfig_args_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        let path = dir.join("custom.yml");
+        fs::write(&path, "test: true").unwrap();
+        let fallback = dir.join("fallback.json");
+        fs::write(&fallback, "{\"test\": false}").unwrap();
+
+        let path_str = path.to_str().unwrap();
+        let fallback_str = fallback.to_str().unwrap();
+        let args = |args: &[&str]| args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+
+        let separated = args(&["prog", "--verbose", "--config", path_str]);
+        let config: TestConfig =
+            files::load_from_args(&separated, "--config", fallback_str).expect("Unable to load");
+        assert!(config.test);
+
+        let joined = args(&["prog", &format!("--config={}", path_str)]);
+        let config: TestConfig =
+            files::load_from_args(&joined, "--config", fallback_str).expect("Unable to load");
+        assert!(config.test);
+
+        let config: TestConfig = files::load_from_args(&args(&["prog"]), "--config", fallback_str)
+            .expect("Unable to load");
+        assert!(!config.test);
+
+        let path_from =
+            |list: &[&str]| files::path_from_args(&args(list), "--config", "default.yml");
+        assert_eq!(
+            path_from(&["--config=a.yml", "--config", "b.yml"]).unwrap(),
+            PathBuf::from("b.yml")
+        );
+        assert_eq!(
+            path_from(&["--configure", "--config-dir=x"]).unwrap(),
+            PathBuf::from("default.yml")
+        );
+        assert_eq!(
+            path_from(&["--", "--config", "b.yml"]).unwrap(),
+            PathBuf::from("default.yml")
+        );
+
+        for missing in [
+            &["--config"][..],
+            &["--config", "--verbose"],
+            &["--config="],
+        ] {
+            let err = path_from(missing).unwrap_err();
+            assert!(matches!(&err, ConfigError::MissingArgValue { flag } if flag == "--config"));
+            assert!(err.to_string().contains("--config"), "{}", err);
+        }
+
+        // the path composes with the save options
+        let target = files::path_from_args(&separated, "--config", fallback_str).unwrap();
+        files::save_with_backup(
+            &TestConfig { test: false },
+            &target,
+            files::BackupPolicy::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            fs::read_to_string(dir.join("custom.yml.bak")).unwrap(),
+            "test: true"
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn flag_config_test() {
+        let args = [
+            "prog",
+            "--verbose",
+            "--Verbose",
+            "--verbose",
+            "--level=3",
+            "---odd",
+            "input",
+            "--key:value",
+        ];
+        let flags = quick::FlagConfig::from_env_args_ref(&args);
+
+        assert_eq!(
+            flags.iter().collect::<Vec<_>>(),
+            ["verbose", "Verbose", "verbose", "level=3", "-odd"]
+        );
+        assert_eq!(flags.len(), 5);
+        assert!(!flags.is_empty());
+
+        // names are case-sensitive, and values don't get in the way
+        assert!(flags.contains("verbose"));
+        assert!(flags.contains("Verbose"));
+        assert!(!flags.contains("VERBOSE"));
+        assert!(flags.contains("level"));
+        assert!(!flags.contains("level=3"));
+        assert!(flags.contains("-odd"));
+        assert!(!flags.contains("key"));
+        assert!(!flags.contains("input"));
+
+        // long flags are written back as they were passed
+        assert_eq!(flags.long_args().collect::<Vec<_>>(), &args[1..6]);
+
+        let borrowed: Vec<&String> = (&flags).into_iter().collect();
+        assert_eq!(borrowed.len(), 5);
+        let owned: Vec<String> = flags.clone().into_iter().collect();
+        assert_eq!(owned, flags.iter().collect::<Vec<_>>());
+        assert_eq!(
+            format!("{:?}", quick::FlagConfig::from_env_args_ref(&["--a"])),
+            "FlagConfig([\"a\"])"
+        );
+
+        let empty = quick::FlagConfig::from_env_args_ref(&["prog", "input"]);
+        assert!(empty.is_empty());
+        assert_eq!(empty.len(), 0);
+    }
+
+    #[test]
+    fn key_value_config_test() {
+        let args = [
+            "prog",
+            "input.txt",
+            "--port:8080",
+            "--url=http://localhost:80/a=b",
+            "--level",
+            "debug",
+            "--level=trace",
+            "--verbose",
+            "--name:a=b",
+            "--quiet",
+            "--",
+            "after",
+            "--=x",
+            "--:y",
+        ];
+        let config = quick::KeyValueConfig::from_env_args_ref(&args);
+
+        assert_eq!(config.get("port"), Some("8080"));
+        assert_eq!(config.get("url"), Some("http://localhost:80/a=b"));
+        assert_eq!(config.get("name"), Some("a=b"));
+        assert_eq!(config.get("level"), Some("trace"));
+        assert_eq!(config.get_all("level"), ["debug", "trace"]);
+        assert_eq!(config.get("verbose"), Some("true"));
+        assert_eq!(config.get("quiet"), Some("true"));
+        assert_eq!(config.get("input.txt"), None);
+        assert_eq!(config.get(""), None);
+        assert_eq!(config.get_or("port", "80"), "8080");
+        assert_eq!(config.get_or("missing", "80"), "80");
+        assert!(config.get_all("missing").is_empty());
+        assert_eq!(config.len(), 7);
+
+        let map = config.to_map();
+        assert_eq!(map.len(), 6);
+        assert_eq!(map["level"], "trace");
+
+        assert_eq!(
+            config.iter().take(2).collect::<Vec<_>>(),
+            [("port", "8080"), ("url", "http://localhost:80/a=b")]
+        );
+
+        assert_eq!(config.trailing_args(), ["after", "--=x", "--:y"]);
+
+        let empty = quick::KeyValueConfig::from_env_args_ref(&["prog"]);
+        assert!(empty.is_empty());
+    }
+
+    #[test]
+    fn key_value_parsed_test() {
+        use std::str::FromStr;
+
+        #[derive(Debug, PartialEq)]
+        enum Level {
+            Info,
+            Debug,
+        }
+
+        impl FromStr for Level {
+            type Err = String;
+
+            fn from_str(s: &str) -> std::result::Result<Self, String> {
+                match s {
+                    "info" => Ok(Self::Info),
+                    "debug" => Ok(Self::Debug),
+                    _ => Err(format!("unknown level `{}`", s)),
+                }
+            }
+        }
+
+        let args = [
+            "--port:8080",
+            "--offset=-3",
+            "--ratio=0.5",
+            "--big=70000",
+            "--level=debug",
+            "--bad-level=loud",
+            "--color=YES",
+            "--fast=off",
+            "--strict=1",
+            "--verbose",
+            "--maybe=perhaps",
+        ];
+        let config = quick::KeyValueConfig::from_env_args_ref(&args);
+
+        assert_eq!(config.get_parsed::<u16>("port"), Ok(8080));
+        assert_eq!(config.get_parsed::<i32>("offset"), Ok(-3));
+        assert_eq!(config.get_parsed::<f64>("ratio"), Ok(0.5));
+        assert_eq!(config.get_parsed::<Level>("level"), Ok(Level::Debug));
+        assert_eq!(config.get_parsed::<bool>("verbose"), Ok(true));
+
+        let err = config.get_parsed::<u16>("big").unwrap_err();
+        assert_eq!(
+            (err.key.as_str(), err.value.as_deref(), err.expected),
+            ("big", Some("70000"), "u16")
+        );
+        assert!(
+            err.to_string().contains("--big") && err.to_string().contains("70000"),
+            "{}",
+            err
+        );
+
+        let err = config.get_parsed::<Level>("bad-level").unwrap_err();
+        assert!(err.expected.ends_with("Level"));
+        assert_eq!(err.message, "unknown level `loud`");
+
+        let err = config.get_parsed::<u16>("missing").unwrap_err();
+        assert_eq!(err.value, None);
+        assert!(err.to_string().contains("--missing"), "{}", err);
+
+        assert_eq!(config.get_bool("color"), Ok(true));
+        assert_eq!(config.get_bool("fast"), Ok(false));
+        assert_eq!(config.get_bool("strict"), Ok(true));
+        assert_eq!(config.get_bool("verbose"), Ok(true));
+        assert_eq!(config.get_bool("missing"), Ok(false));
+        assert_eq!(config.get_bool("maybe").unwrap_err().expected, "bool");
+
+        assert!(config.has_flag("verbose"));
+        assert!(config.has_flag("port"));
+        assert!(!config.has_flag("missing"));
+    }
+
+    #[test]
+    fn short_flags_test() {
+        let args = [
+            "prog", "-v", "-xzf", "-", "-1", "-2.5", "-.5", "--long", "-o2", "-x=1", "-x2y",
+            "-vn10", "-e=", "-", "input",
+        ];
+        let flags = quick::FlagConfig::from_env_args_ref(&args);
+
+        // values stay on the last letter of a group, and other single-dash arguments aren't flags
+        assert_eq!(
+            flags.iter().collect::<Vec<_>>(),
+            ["v", "x", "z", "f", "long", "o=2", "x=1", "v", "n=10", "e="]
+        );
+        assert_eq!(
+            quick::FlagConfig::from_env_args(args.iter().map(|s| s.to_string()).collect()),
+            flags
+        );
+
+        // borrowed parsing reads short flags the same way
+        let view = quick::FlagView::parse(args.into_iter());
+        assert_eq!(view.to_flag_config(), flags);
+        assert!(view.has("z"));
+        assert_eq!(view.value_of("o"), Some("2"));
+        assert_eq!(view.value_of("n"), Some("10"));
+        assert_eq!(view.value_of("x"), Some("1"));
+        assert_eq!(view.value_of("v"), None);
+        assert!(!view.has("2"));
+        assert!(!view.has("y"));
+
+        let flags = flags.with_aliases(&[
+            ('v', "verbose"),
+            ('f', "file"),
+            ('q', "quiet"),
+            ('n', "count"),
+        ]);
+        assert!(flags.iter().any(|flag| flag == "count=10"));
+        assert!(flags.contains("verbose"));
+        assert!(flags.contains("file"));
+        assert!(flags.contains("x"));
+        assert!(!flags.contains("v"));
+        assert!(!flags.contains("quiet"));
+        // long flags aren't renamed
+        assert!(flags.contains("long"));
+        // short flags are written back in their long form
+        assert_eq!(
+            flags.long_args().take(2).collect::<Vec<_>>(),
+            ["--verbose", "--x"]
+        );
+
+        // canonical args parse back into the same flags
+        let reparsed = quick::FlagConfig::from_env_args(flags.canonical_args());
+        assert_eq!(reparsed, flags);
+    }
+
+    #[test]
+    fn negation_test() {
+        let flags = |args: &[&str]| quick::FlagConfig::from_env_args_ref(args);
+
+        assert_eq!(flags(&["--x", "--no-x"]).flag_state("x"), Some(false));
+        assert_eq!(flags(&["--no-x", "--x"]).flag_state("x"), Some(true));
+        assert_eq!(flags(&["--no-x"]).flag_state("x"), Some(false));
+        assert_eq!(flags(&["--x"]).flag_state("x"), Some(true));
+        assert_eq!(flags(&["--x=1", "--y"]).flag_state("x"), Some(true));
+        assert_eq!(flags(&["--y", "--no-xy"]).flag_state("x"), None);
+        assert_eq!(flags(&[]).flag_state("x"), None);
+
+        let view = |args: &'static [&'static str]| quick::FlagView::parse(args.iter().copied());
+
+        assert_eq!(view(&["--x", "--no-x"]).flag_state("x"), Some(false));
+        assert_eq!(view(&["--no-x", "--x"]).flag_state("x"), Some(true));
+        assert_eq!(view(&["--no-x"]).flag_state("x"), Some(false));
+        assert_eq!(view(&["--x=1", "--y"]).flag_state("x"), Some(true));
+        assert_eq!(view(&["--y", "--no-xy"]).flag_state("x"), None);
+        assert_eq!(view(&["--", "--x"]).flag_state("x"), None);
+
+        // the raw flags are still there
+        let raw = flags(&["--x", "--no-x"]);
+        assert!(raw.contains("x"));
+        assert!(raw.contains("no-x"));
+        assert_eq!(raw.len(), 2);
+
+        let values = |args: &[&str]| quick::KeyValueConfig::from_env_args_ref(args);
+
+        assert_eq!(values(&["--x", "--no-x"]).get_bool("x"), Ok(false));
+        assert_eq!(values(&["--no-x", "--x"]).get_bool("x"), Ok(true));
+        assert_eq!(values(&["--no-x"]).get_bool("x"), Ok(false));
+        assert_eq!(values(&["--no-x=false"]).get_bool("x"), Ok(true));
+        assert_eq!(values(&["--x=off", "--y"]).get_bool("x"), Ok(false));
+
+        let err = values(&["--x", "--no-x=maybe"]).get_bool("x").unwrap_err();
+        assert_eq!(err.key, "no-x");
+    }
+
+    #[test]
+    fn trailing_args_test() {
+        let args = [
+            "mytool",
+            "--verbose",
+            "-x",
+            "--",
+            "--not-a-flag",
+            "-y",
+            "--",
+            "file.txt",
+        ];
+        let trailing = ["--not-a-flag", "-y", "--", "file.txt"];
+
+        let flags = quick::FlagConfig::from_env_args_ref(&args);
+        assert_eq!(flags.iter().collect::<Vec<_>>(), ["verbose", "x"]);
+        assert!(!flags.contains("not-a-flag"));
+        assert_eq!(
+            quick::FlagConfig::from_env_args(args.iter().map(|s| s.to_string()).collect()),
+            flags
+        );
+
+        let (split, split_trailing) =
+            quick::FlagConfig::split(args.iter().map(|s| s.to_string()).collect());
+        assert_eq!(split, flags);
+        assert_eq!(split_trailing, trailing);
+
+        let (_, split_trailing) = quick::FlagConfig::split(vec!["--a".into(), "--".into()]);
+        assert!(split_trailing.is_empty());
+        let (_, split_trailing) = quick::FlagConfig::split(vec!["--a".into()]);
+        assert!(split_trailing.is_empty());
+
+        let view = quick::FlagView::parse(args.into_iter());
+        assert!(view.has("verbose"));
+        assert!(!view.has("not-a-flag"));
+        assert_eq!(view.trailing_args(), trailing);
+        assert_eq!(view.to_flag_config(), flags);
+
+        let values = quick::KeyValueConfig::from_env_args_ref(&[
+            "--level",
+            "debug",
+            "--",
+            "--level=trace",
+            "x",
+        ]);
+        assert_eq!(values.get("level"), Some("debug"));
+        assert_eq!(values.trailing_args(), ["--level=trace", "x"]);
+
+        // a `--` right after a key isn't taken as its value
+        let values = quick::KeyValueConfig::from_env_args_ref(&["--verbose", "--", "input"]);
+        assert_eq!(values.get("verbose"), Some("true"));
+        assert_eq!(values.trailing_args(), ["input"]);
+
+        #[cfg(unix)]
+        {
+            let fd = quick::ConfigFdArg::from_env_args_ref(&["prog", "--", "--config-fd", "3"]);
+            assert_eq!(fd.0, None);
+        }
     }
 }
@@ -0,0 +1,188 @@
+use crate::*;
+
+use std::{
+    any::{Any, TypeId},
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{Arc, Condvar, Mutex, MutexGuard, OnceLock},
+};
+
+type Shared = Arc<dyn Any + Send + Sync>;
+type Key = (PathBuf, TypeId);
+
+// what a finished load left behind for the callers waiting on it
+enum Outcome {
+    Loaded(Shared),
+    Failed(Arc<ConfigError>),
+    // the loading thread panicked, waiters should start over
+    Abandoned,
+}
+
+#[derive(Default)]
+struct Pending {
+    outcome: Mutex<Option<Outcome>>,
+    ready: Condvar,
+}
+
+impl Pending {
+    fn finish(&self, outcome: Outcome) {
+        *self.outcome.lock().unwrap() = Some(outcome);
+        self.ready.notify_all();
+    }
+
+    fn wait(&self) -> MutexGuard<'_, Option<Outcome>> {
+        let mut outcome = self.outcome.lock().unwrap();
+        while outcome.is_none() {
+            outcome = self.ready.wait(outcome).unwrap();
+        }
+
+        outcome
+    }
+}
+
+enum Entry {
+    Loading(Arc<Pending>),
+    Loaded(Shared),
+}
+
+fn entries() -> MutexGuard<'static, HashMap<Key, Entry>> {
+    static ENTRIES: OnceLock<Mutex<HashMap<Key, Entry>>> = OnceLock::new();
+
+    ENTRIES.get_or_init(Default::default).lock().unwrap()
+}
+
+// clears the registry entry if the loading thread unwinds before finishing
+struct LoadGuard {
+    key: Key,
+    pending: Arc<Pending>,
+    finished: bool,
+}
+
+impl LoadGuard {
+    fn finish(mut self, outcome: Outcome) {
+        let mut entries = entries();
+
+        // an invalidate() during the load means the result shouldn't be cached
+        if let Some(Entry::Loading(current)) = entries.get(&self.key) {
+            if Arc::ptr_eq(current, &self.pending) {
+                match &outcome {
+                    Outcome::Loaded(config) => {
+                        entries.insert(self.key.clone(), Entry::Loaded(config.clone()));
+                    }
+                    _ => {
+                        entries.remove(&self.key);
+                    }
+                }
+            }
+        }
+
+        drop(entries);
+
+        self.pending.finish(outcome);
+        self.finished = true;
+    }
+}
+
+impl Drop for LoadGuard {
+    fn drop(&mut self) {
+        if !self.finished {
+            let mut entries = entries();
+            if let Some(Entry::Loading(current)) = entries.get(&self.key) {
+                if Arc::ptr_eq(current, &self.pending) {
+                    entries.remove(&self.key);
+                }
+            }
+            drop(entries);
+
+            self.pending.finish(Outcome::Abandoned);
+        }
+    }
+}
+
+fn downcast<T: Send + Sync + 'static>(config: Shared) -> Arc<T> {
+    // keys include the TypeId, so this can't fail
+    config.downcast().ok().unwrap()
+}
+
+/// Loads a configuration struct from a file once per process and shares it.
+/// The first caller for a path performs the load using [`files::load_from_file`],
+/// while concurrent callers block until it finishes and receive the same `Arc<T>` (or the same `Arc<ConfigError>`).
+/// Later calls return the cached `Arc<T>` until [`invalidate`] is called for the path.
+///
+/// Paths are compared as given (they aren't canonicalized), and each config type gets its own entry.
+/// A failed load isn't cached, so the next call retries.
+/// If the loading thread panics, the waiting callers retry the load instead of deadlocking.
+/// ### Example
+/// ```rust,no_run
+/// # use rsconfig::{once, FileConfig};
+/// # fn example<T: FileConfig + Send + Sync + 'static>() {
+/// // every subsystem can call this at startup, the file is only read once
+/// let config = once::load_shared::<T>("config.yml").expect("Unable to load config");
+/// # }
+/// ```
+pub fn load_shared<T: FileConfig + Send + Sync + 'static>(
+    path: impl AsRef<Path>,
+) -> Result<Arc<T>, Arc<ConfigError>> {
+    let path = path.as_ref();
+    let key = (path.to_path_buf(), TypeId::of::<T>());
+
+    loop {
+        let pending = {
+            let mut entries = entries();
+
+            match entries.get(&key) {
+                Some(Entry::Loaded(config)) => return Ok(downcast(config.clone())),
+                Some(Entry::Loading(pending)) => pending.clone(),
+                None => {
+                    let pending = Arc::new(Pending::default());
+                    entries.insert(key.clone(), Entry::Loading(pending.clone()));
+                    drop(entries);
+
+                    return load(key, pending, path);
+                }
+            }
+        };
+
+        let outcome = pending.wait();
+        match &*outcome {
+            Some(Outcome::Loaded(config)) => return Ok(downcast(config.clone())),
+            Some(Outcome::Failed(e)) => return Err(e.clone()),
+            _ => continue,
+        }
+    }
+}
+
+fn load<T: FileConfig + Send + Sync + 'static>(
+    key: Key,
+    pending: Arc<Pending>,
+    path: &Path,
+) -> Result<Arc<T>, Arc<ConfigError>> {
+    let guard = LoadGuard {
+        key,
+        pending,
+        finished: false,
+    };
+
+    match files::load_from_file::<T>(path) {
+        Ok(config) => {
+            let config = Arc::new(config);
+            guard.finish(Outcome::Loaded(config.clone()));
+
+            Ok(config)
+        }
+        Err(e) => {
+            let e = Arc::new(e);
+            guard.finish(Outcome::Failed(e.clone()));
+
+            Err(e)
+        }
+    }
+}
+
+/// Forgets the shared configs loaded from a path, so the next [`load_shared`] call reads the file again.
+/// `Arc`s handed out earlier stay valid. A load already in progress still completes for its callers, but its result isn't cached.
+pub fn invalidate(path: impl AsRef<Path>) {
+    let path = path.as_ref();
+
+    entries().retain(|(entry_path, _), _| entry_path != path);
+}
@@ -0,0 +1,247 @@
+use std::{error::Error, fmt};
+
+/// Parsed contents of a Java-style `.properties` file, keeping keys in file order.
+/// Parsing follows `java.util.Properties`:
+/// - `key=value`, `key: value` and `key value` are all accepted, with whitespace around the separator ignored
+/// - lines starting with `#` or `!` are comments
+/// - a line ending in an unescaped `\` continues on the next line, whose leading whitespace is skipped
+/// - `\t`, `\n`, `\r`, `\f` and `\uXXXX` escapes are understood, and any other escaped character stands for itself
+///
+/// A repeated key replaces the earlier value but keeps its original position,
+/// so writing the file back with `to_string` keeps keys in a stable order.
+/// ### Example
+/// ```rust
+/// use rsconfig::properties::Properties;
+///
+/// let props: Properties = "app.name = demo\\\n    app\ngreeting: caf\\u00e9\n".parse().unwrap();
+///
+/// assert_eq!(props.get("app.name"), Some("demoapp"));
+/// assert_eq!(props.get("greeting"), Some("café"));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Properties {
+    entries: Vec<(String, String)>,
+}
+
+impl Properties {
+    /// Creates an empty properties document.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the value of a key.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.entries
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Sets a key, replacing its value in place if it already exists or adding it at the end otherwise.
+    pub fn set(&mut self, key: impl Into<String>, value: impl Into<String>) {
+        let key = key.into();
+        let value = value.into();
+
+        match self.entries.iter_mut().find(|(k, _)| *k == key) {
+            Some((_, existing)) => *existing = value,
+            None => self.entries.push((key, value)),
+        }
+    }
+
+    /// Removes a key, returning its value.
+    pub fn remove(&mut self, key: &str) -> Option<String> {
+        let index = self.entries.iter().position(|(k, _)| k == key)?;
+
+        Some(self.entries.remove(index).1)
+    }
+
+    /// Iterates over the keys and values, in file order.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.entries.iter().map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+
+    /// Returns the number of keys.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns true if there are no keys.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+/// Error returned when a properties document can't be parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PropertiesParseError {
+    /// Line the error was found on, starting from 1
+    pub line: usize,
+    /// Description of what was wrong with the line
+    pub message: String,
+}
+
+impl fmt::Display for PropertiesParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl Error for PropertiesParseError {}
+
+impl std::str::FromStr for Properties {
+    type Err = PropertiesParseError;
+
+    fn from_str(data: &str) -> Result<Self, Self::Err> {
+        let mut props = Properties::new();
+        let mut lines = data.lines().enumerate();
+
+        while let Some((i, line)) = lines.next() {
+            let line = line.trim_start();
+
+            if line.is_empty() || line.starts_with('#') || line.starts_with('!') {
+                continue;
+            }
+
+            // join continuation lines into one logical line
+            let mut logical = line.to_string();
+            while ends_with_continuation(&logical) {
+                logical.pop();
+
+                match lines.next() {
+                    Some((_, next)) => logical.push_str(next.trim_start()),
+                    None => break,
+                }
+            }
+
+            let (key, value) = split_entry(&logical);
+            let error = |message: String| PropertiesParseError {
+                line: i + 1,
+                message,
+            };
+
+            props.set(
+                unescape(key).map_err(error)?,
+                unescape(value).map_err(error)?,
+            );
+        }
+
+        Ok(props)
+    }
+}
+
+// an odd number of trailing backslashes means the last one escapes the line break
+fn ends_with_continuation(line: &str) -> bool {
+    line.chars().rev().take_while(|c| *c == '\\').count() % 2 == 1
+}
+
+// splits a logical line into its raw (still escaped) key and value
+fn split_entry(line: &str) -> (&str, &str) {
+    let mut escaped = false;
+    let mut key_end = line.len();
+
+    for (i, c) in line.char_indices() {
+        if escaped {
+            escaped = false;
+        } else if c == '\\' {
+            escaped = true;
+        } else if matches!(c, '=' | ':' | ' ' | '\t' | '\x0c') {
+            key_end = i;
+            break;
+        }
+    }
+
+    let rest = line[key_end..].trim_start_matches([' ', '\t', '\x0c']);
+    let rest = rest.strip_prefix(['=', ':']).unwrap_or(rest);
+
+    (
+        &line[..key_end],
+        rest.trim_start_matches([' ', '\t', '\x0c']),
+    )
+}
+
+fn unescape(raw: &str) -> Result<String, String> {
+    let mut value = String::with_capacity(raw.len());
+    let mut chars = raw.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            value.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            Some('t') => value.push('\t'),
+            Some('n') => value.push('\n'),
+            Some('r') => value.push('\r'),
+            Some('f') => value.push('\x0c'),
+            Some('u') => {
+                let unit = read_unicode(&mut chars)?;
+
+                // characters outside the BMP are written as a surrogate pair
+                let c = if (0xD800..0xDC00).contains(&unit) {
+                    let low = match (chars.next(), chars.next()) {
+                        (Some('\\'), Some('u')) => read_unicode(&mut chars)?,
+                        _ => return Err("unpaired surrogate in \\u escape".to_string()),
+                    };
+
+                    char::decode_utf16([unit, low]).next().and_then(Result::ok)
+                } else {
+                    char::from_u32(unit as u32)
+                };
+
+                value.push(c.ok_or("unpaired surrogate in \\u escape")?);
+            }
+            Some(other) => value.push(other),
+            None => {}
+        }
+    }
+
+    Ok(value)
+}
+
+fn read_unicode(chars: &mut std::str::Chars) -> Result<u16, String> {
+    let digits: String = chars.take(4).collect();
+
+    // from_str_radix also takes a sign, so `\u+123` has to be ruled out first
+    if digits.len() != 4 || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(format!("malformed \\u escape `\\u{}`", digits));
+    }
+
+    u16::from_str_radix(&digits, 16).map_err(|e| e.to_string())
+}
+
+// non-ASCII characters are written as \u escapes so Java's ISO-8859-1 loader reads them back correctly
+fn escape(raw: &str, is_key: bool, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    for (i, c) in raw.chars().enumerate() {
+        match c {
+            '\\' => f.write_str("\\\\")?,
+            '\t' => f.write_str("\\t")?,
+            '\n' => f.write_str("\\n")?,
+            '\r' => f.write_str("\\r")?,
+            '\x0c' => f.write_str("\\f")?,
+            '=' | ':' | '#' | '!' => write!(f, "\\{}", c)?,
+            ' ' if is_key || i == 0 => f.write_str("\\ ")?,
+            ' '..='~' => write!(f, "{}", c)?,
+            _ => {
+                for unit in c.encode_utf16(&mut [0; 2]) {
+                    write!(f, "\\u{:04X}", unit)?;
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+impl fmt::Display for Properties {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (key, value) in &self.entries {
+            escape(key, true, f)?;
+            f.write_str("=")?;
+            escape(value, false, f)?;
+            writeln!(f)?;
+        }
+
+        Ok(())
+    }
+}
@@ -1,20 +1,589 @@
 use crate::*;
 
+use std::{collections::HashMap, error::Error, fmt, str::FromStr};
+
+#[cfg(unix)]
+use std::os::fd::RawFd;
+
 /// Creates a list of commandline flags that were used in command arguments
-/// Command line flags are any arguments that start with `--` and do not contain `:`
+/// Command line flags are any arguments that start with `--` and do not contain `:`,
+/// and short flags like `-v`, with grouped short flags like `-xvf` read as `-x -v -f`.
+/// A short group can end with a value for its last letter, either digits (`-n10`) or after `=` (`-x=1`),
+/// which is kept like a long flag's value, as `n=10` and `x=1`. Short flags are ASCII letters, so other arguments
+/// starting with a single `-`, like a bare `-` (usually stdin), negative numbers like `-1` or `-x2y`, aren't flags.
 /// Useful for arbitrary options when you just want to quickstart a project instead of creating your own CommandlineConfig
+///
+/// Flags are stored without their leading dashes, so `--verbose` is kept as `verbose`, `--level=3` as `level=3` and `-v` as `v`.
+/// Use [`FlagConfig::with_aliases`] to give short flags their long names.
+/// Arguments after a literal `--` aren't read as flags, use [`FlagConfig::split`] to keep them.
+/// They're kept in the order they were encountered, repeated flags are kept once per occurrence, and names are case-sensitive.
+/// Debug output, iteration and [`FlagConfig::canonical_args`] follow the same order.
+/// ### Example
+/// ```rust,no_run
+/// use rsconfig::{quick::FlagConfig, CommandlineConfig};
+///
+/// // the program name is left out, so only the flags the user passed are collected
+/// let flags = FlagConfig::from_current_args();
+///
+/// if flags.contains("verbose") {
+///     println!("flags: {:?}", flags);
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct FlagConfig(Vec<String>);
 
+impl FlagConfig {
+    /// Checks whether a flag was passed, either bare (`--name`) or with a value (`--name=value`).
+    pub fn contains(&self, name: &str) -> bool {
+        self.iter()
+            .any(|flag| flag.split_once('=').map_or(flag, |(flag, _)| flag) == name)
+    }
+
+    /// Returns whether a flag is on, with `--no-<name>` turning it off: Some(true) if the last of the two was `--name`,
+    /// Some(false) if it was `--no-name`, and None if neither was passed. Like [`FlagConfig::contains`], values are ignored.
+    /// The flags are kept as they were passed, so `contains("no-color")` still works for callers who don't want negation.
+    /// ### Example
+    /// ```rust
+    /// use rsconfig::{quick::FlagConfig, CommandlineConfig};
+    ///
+    /// // --color came from a shell alias, --no-color from the user
+    /// let flags = FlagConfig::from_env_args_ref(&["prog", "--color", "--no-color"]);
+    ///
+    /// assert_eq!(flags.flag_state("color"), Some(false));
+    /// assert_eq!(flags.flag_state("verbose"), None);
+    /// ```
+    pub fn flag_state(&self, name: &str) -> Option<bool> {
+        self.iter().rev().find_map(|flag| {
+            let flag = flag.split_once('=').map_or(flag, |(flag, _)| flag);
+
+            if flag == name {
+                Some(true)
+            } else if flag.strip_prefix("no-") == Some(name) {
+                Some(false)
+            } else {
+                None
+            }
+        })
+    }
+
+    /// Iterates over the flags without their leading `--`, in the order they were encountered.
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &str> {
+        self.0.iter().map(String::as_str)
+    }
+
+    /// Returns the number of flags, counting repeated flags once per occurrence.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Returns true if no flags were passed.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Iterates over the flags written as long arguments, like `--verbose` or `--level=3`.
+    /// These aren't the original arguments: `-v` comes back as `--v`, or as `--verbose` after [`FlagConfig::with_aliases`].
+    pub fn long_args(&self) -> impl Iterator<Item = String> + '_ {
+        self.iter().map(|flag| format!("--{}", flag))
+    }
+
+    /// Renames one-letter flags to their long names, so `-v` can be checked with `contains("verbose")`.
+    /// ### Example
+    /// ```rust
+    /// use rsconfig::{quick::FlagConfig, CommandlineConfig};
+    ///
+    /// let flags = FlagConfig::from_env_args_ref(&["prog", "-vf", "-", "-1"]).with_aliases(&[('v', "verbose")]);
+    ///
+    /// assert!(flags.contains("verbose"));
+    /// assert!(flags.contains("f"));
+    /// assert_eq!(flags.len(), 2);
+    /// ```
+    pub fn with_aliases(mut self, aliases: &[(char, &str)]) -> Self {
+        for flag in &mut self.0 {
+            let (letter, value) = flag
+                .split_once('=')
+                .map_or((flag.as_str(), None), |(name, value)| (name, Some(value)));
+            let mut letters = letter.chars();
+
+            if let (Some(letter), None) = (letters.next(), letters.next()) {
+                if let Some((_, name)) = aliases.iter().find(|(short, _)| *short == letter) {
+                    *flag = flag_string(name, value);
+                }
+            }
+        }
+
+        self
+    }
+
+    /// Collects the flags like `from_env_args`, and also returns the arguments after the first `--`, as they were passed.
+    /// Later `--` arguments are kept in the list.
+    /// ### Example
+    /// ```rust
+    /// use rsconfig::quick::FlagConfig;
+    ///
+    /// let args = ["mytool", "--verbose", "--", "--not-a-flag", "file.txt"].map(String::from).to_vec();
+    /// let (flags, trailing) = FlagConfig::split(args);
+    ///
+    /// assert!(!flags.contains("not-a-flag"));
+    /// assert_eq!(trailing, ["--not-a-flag", "file.txt"]);
+    /// ```
+    pub fn split(mut args: Vec<String>) -> (Self, Vec<String>) {
+        let trailing = match args.iter().position(|arg| arg == "--") {
+            Some(i) => args.drain(i..).skip(1).collect(),
+            None => Vec::new(),
+        };
+
+        (Self::from_env_args(args), trailing)
+    }
+
+    /// Reconstructs a list of arguments that parses back into an equal FlagConfig.
+    /// Non-flag arguments are dropped, and flags appear in the order they were encountered.
+    pub fn canonical_args(&self) -> Vec<String> {
+        self.long_args().collect()
+    }
+}
+
+impl IntoIterator for FlagConfig {
+    type Item = String;
+    type IntoIter = std::vec::IntoIter<String>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a FlagConfig {
+    type Item = &'a String;
+    type IntoIter = std::slice::Iter<'a, String>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+
+fn is_flag(arg: &str) -> bool {
+    arg.starts_with("--") && !arg.contains(':')
+}
+
+// the letters and value of `-v`, `-xvf`, `-n10` or `-x=1`, but not `-` (stdin), negative numbers like `-1` or `-x2y`
+fn short_group(arg: &str) -> Option<(&str, Option<&str>)> {
+    let rest = arg.strip_prefix('-')?;
+    let end = rest
+        .find(|c: char| !c.is_ascii_alphabetic())
+        .unwrap_or(rest.len());
+
+    if end == 0 {
+        return None;
+    }
+
+    let (letters, tail) = rest.split_at(end);
+    let value = if tail.is_empty() {
+        None
+    } else if let Some(value) = tail.strip_prefix('=') {
+        Some(value)
+    } else if tail.bytes().all(|b| b.is_ascii_digit()) {
+        Some(tail)
+    } else {
+        return None;
+    };
+
+    Some((letters, value))
+}
+
+// splits the arguments at the first `--`, the ones after it aren't interpreted
+fn split_trailing<S: AsRef<str>>(args: &[S]) -> (&[S], &[S]) {
+    match args.iter().position(|arg| arg.as_ref() == "--") {
+        Some(i) => (&args[..i], &args[i + 1..]),
+        None => (args, &[]),
+    }
+}
+
+// the flags in the arguments as names and values, long flags without their `--`
+// and short groups split into one name per letter, with the group's value on the last one
+fn flag_names<'a>(args: impl Iterator<Item = &'a str>) -> Vec<(&'a str, Option<&'a str>)> {
+    let mut names = Vec::new();
+
+    for arg in args {
+        if is_flag(arg) {
+            let flag = &arg[2..];
+
+            names.push(match flag.split_once('=') {
+                Some((name, value)) => (name, Some(value)),
+                None => (flag, None),
+            });
+        } else if let Some((letters, value)) = short_group(arg) {
+            let last = letters.len() - 1;
+
+            names.extend(
+                (0..letters.len())
+                    .map(|i| (&letters[i..i + 1], if i == last { value } else { None })),
+            );
+        }
+    }
+
+    names
+}
+
+// the way FlagConfig stores a flag, `name` or `name=value`
+fn flag_string(name: &str, value: Option<&str>) -> String {
+    match value {
+        Some(value) => format!("{}={}", name, value),
+        None => name.to_string(),
+    }
+}
+
 impl CommandlineConfig for FlagConfig {
     fn from_env_args(args: Vec<String>) -> Self {
-        let mut flags = Vec::new();
+        let (args, _) = split_trailing(&args);
 
-        for arg in args {
-            if arg.starts_with("--") && !arg.contains(":") {
-                flags.push(arg);
+        Self(
+            flag_names(args.iter().map(String::as_str))
+                .into_iter()
+                .map(|(name, value)| flag_string(name, value))
+                .collect(),
+        )
+    }
+
+    fn from_env_args_ref(args: &[&str]) -> Self {
+        let (args, _) = split_trailing(args);
+
+        Self(
+            flag_names(args.iter().copied())
+                .into_iter()
+                .map(|(name, value)| flag_string(name, value))
+                .collect(),
+        )
+    }
+}
+
+/// Collects key-value commandline arguments, like `--port:8080`, `--port=8080` or `--port 8080`
+/// Useful for quickstarting options that need a value, alongside FlagConfig for the ones that don't
+///
+/// - the key ends at the first `=` or `:`, so `--url=http://localhost` has the value `http://localhost`
+/// - `--key value` takes the next argument as the value, unless it starts with `--`
+/// - a key with no value (like `--verbose` at the end) is stored with the value `true`
+/// - repeated keys keep every value, [`KeyValueConfig::get`] returns the last one and [`KeyValueConfig::get_all`] all of them
+/// - arguments that aren't keys or values, like the program name, are ignored
+/// - arguments after a literal `--` aren't read, and are kept in [`KeyValueConfig::trailing_args`]
+/// ### Example
+/// ```rust
+/// use rsconfig::{quick::KeyValueConfig, CommandlineConfig};
+///
+/// let config = KeyValueConfig::from_env_args_ref(&["prog", "--port:8080", "--host", "localhost", "--tag=a", "--tag=b", "--verbose"]);
+///
+/// assert_eq!(config.get("port"), Some("8080"));
+/// assert_eq!(config.get("host"), Some("localhost"));
+/// assert_eq!(config.get_all("tag"), ["a", "b"]);
+/// assert_eq!(config.get("verbose"), Some("true"));
+/// assert_eq!(config.get_or("level", "info"), "info");
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct KeyValueConfig {
+    pairs: Vec<(String, String)>,
+    trailing: Vec<String>,
+}
+
+impl KeyValueConfig {
+    /// Returns the value of the last occurrence of a key.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.pairs
+            .iter()
+            .rev()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Returns the value of a key, or `default` if it wasn't passed.
+    pub fn get_or<'a>(&'a self, key: &str, default: &'a str) -> &'a str {
+        self.get(key).unwrap_or(default)
+    }
+
+    /// Returns every value of a key, in the order they were passed.
+    pub fn get_all(&self, key: &str) -> Vec<&str> {
+        self.pairs
+            .iter()
+            .filter(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+            .collect()
+    }
+
+    /// Iterates over the keys and values in the order they were passed, repeated keys included.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.pairs.iter().map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+
+    /// Returns the number of keys passed, counting repeated keys once per occurrence.
+    pub fn len(&self) -> usize {
+        self.pairs.len()
+    }
+
+    /// Returns true if no keys were passed.
+    pub fn is_empty(&self) -> bool {
+        self.pairs.is_empty()
+    }
+
+    /// Returns the arguments after the first `--`, as they were passed. Later `--` arguments are kept in the list.
+    pub fn trailing_args(&self) -> &[String] {
+        &self.trailing
+    }
+
+    /// Collects the keys into a map, keeping the last value of repeated keys.
+    pub fn to_map(&self) -> HashMap<String, String> {
+        self.pairs.iter().cloned().collect()
+    }
+
+    /// Checks whether a key was passed, with or without a value.
+    pub fn has_flag(&self, key: &str) -> bool {
+        self.pairs.iter().any(|(k, _)| k == key)
+    }
+
+    /// Parses the last value of a key with `FromStr`, returning an error naming the key, the value and the type if it's missing or can't be parsed.
+    /// ### Example
+    /// ```rust
+    /// use rsconfig::{quick::KeyValueConfig, CommandlineConfig};
+    ///
+    /// let config = KeyValueConfig::from_env_args_ref(&["--port:8080", "--ratio=abc"]);
+    ///
+    /// assert_eq!(config.get_parsed::<u16>("port"), Ok(8080));
+    /// assert_eq!(
+    ///     config.get_parsed::<f64>("ratio").unwrap_err().to_string(),
+    ///     "--ratio: expected f64, got \"abc\" (invalid float literal)"
+    /// );
+    /// ```
+    pub fn get_parsed<T>(&self, key: &str) -> Result<T, FlagParseError>
+    where
+        T: FromStr,
+        T::Err: fmt::Display,
+    {
+        let error = |value: Option<&str>, message: String| FlagParseError {
+            key: key.to_string(),
+            value: value.map(str::to_string),
+            expected: std::any::type_name::<T>(),
+            message,
+        };
+
+        let value = self
+            .get(key)
+            .ok_or_else(|| error(None, "no value was passed".to_string()))?;
+
+        value
+            .parse()
+            .map_err(|e: T::Err| error(Some(value), e.to_string()))
+    }
+
+    /// Reads a key as a boolean, understanding `true/false`, `yes/no`, `on/off` and `1/0` in any case.
+    /// A key passed without a value (like `--verbose`) is true, and a missing key is false.
+    /// `--no-<key>` negates the key, and whichever of the two was passed last wins, so `--color --no-color` is false.
+    pub fn get_bool(&self, key: &str) -> Result<bool, FlagParseError> {
+        let negated = format!("no-{}", key);
+        let last = self
+            .pairs
+            .iter()
+            .rev()
+            .find(|(k, _)| *k == key || *k == negated);
+
+        let Some((found, value)) = last else {
+            return Ok(false);
+        };
+
+        let state = match value.to_lowercase().as_str() {
+            "true" | "yes" | "on" | "1" => true,
+            "false" | "no" | "off" | "0" => false,
+            _ => {
+                return Err(FlagParseError {
+                    key: found.clone(),
+                    value: Some(value.clone()),
+                    expected: "bool",
+                    message: "use true/false, yes/no, on/off or 1/0".to_string(),
+                })
+            }
+        };
+
+        Ok(if *found == negated { !state } else { state })
+    }
+}
+
+/// Error returned when a [`KeyValueConfig`] value can't be parsed as the requested type.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FlagParseError {
+    /// The key, without the leading `--`
+    pub key: String,
+    /// The value that was passed, or None if the key was missing
+    pub value: Option<String>,
+    /// Name of the type that was requested
+    pub expected: &'static str,
+    /// Description of why the value couldn't be parsed
+    pub message: String,
+}
+
+impl fmt::Display for FlagParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.value {
+            Some(value) => write!(
+                f,
+                "--{}: expected {}, got {:?} ({})",
+                self.key, self.expected, value, self.message
+            ),
+            None => write!(
+                f,
+                "--{}: expected {}, {}",
+                self.key, self.expected, self.message
+            ),
+        }
+    }
+}
+
+impl Error for FlagParseError {}
+
+impl CommandlineConfig for KeyValueConfig {
+    fn from_env_args(args: Vec<String>) -> Self {
+        let (args, trailing) = split_trailing(&args);
+
+        let mut pairs = Vec::new();
+        let mut args = args.iter().peekable();
+
+        while let Some(arg) = args.next() {
+            let Some(arg) = arg.strip_prefix("--").filter(|arg| !arg.is_empty()) else {
+                continue;
+            };
+
+            let (key, value) = match arg.find(['=', ':']) {
+                Some(i) => (&arg[..i], arg[i + 1..].to_string()),
+                None => {
+                    let value = args.next_if(|next| !next.starts_with("--"));
+                    (arg, value.cloned().unwrap_or_else(|| "true".to_string()))
+                }
+            };
+
+            if !key.is_empty() {
+                pairs.push((key.to_string(), value));
+            }
+        }
+
+        Self {
+            pairs,
+            trailing: trailing.to_vec(),
+        }
+    }
+}
+
+/// Borrowed counterpart of FlagConfig that keeps slices of the arguments instead of copying them
+/// Uses the same rules as FlagConfig for `--` flags and short flags, and only allocates owned strings when asked with `to_flag_config`
+/// Arguments after a literal `--` aren't read as flags, and are kept in `trailing_args`
+/// Flag names are given without their leading dashes, and `--key=value` or `-n10` flags are split without allocating
+/// ### Example
+/// ```rust
+/// use rsconfig::quick::FlagView;
+///
+/// let flags = FlagView::parse(["prog", "--verbose", "--level=3"].into_iter());
+///
+/// assert!(flags.has("verbose"));
+/// assert_eq!(flags.value_of("level"), Some("3"));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FlagView<'a> {
+    flags: Vec<(&'a str, Option<&'a str>)>,
+    trailing: Vec<&'a str>,
+}
+
+impl<'a> FlagView<'a> {
+    /// Collects the flags from borrowed commandline arguments.
+    pub fn parse(mut args: impl Iterator<Item = &'a str>) -> Self {
+        // take_while also consumes the `--`, leaving only the trailing arguments
+        let flags = flag_names(args.by_ref().take_while(|arg| *arg != "--"));
+
+        Self {
+            flags,
+            trailing: args.collect(),
+        }
+    }
+
+    /// Checks whether a flag was passed, either bare (`--name`) or with a value (`--name=value`).
+    pub fn has(&self, name: &str) -> bool {
+        self.flags.iter().any(|(flag, _)| *flag == name)
+    }
+
+    /// Returns whether a flag is on, with `--no-<name>` turning it off, like [`FlagConfig::flag_state`].
+    /// The last of `--name` and `--no-name` wins, and None means neither was passed.
+    /// ### Example
+    /// ```rust
+    /// use rsconfig::quick::FlagView;
+    ///
+    /// let flags = FlagView::parse(["prog", "--color", "--no-color"].into_iter());
+    ///
+    /// assert_eq!(flags.flag_state("color"), Some(false));
+    /// assert_eq!(flags.flag_state("verbose"), None);
+    /// ```
+    pub fn flag_state(&self, name: &str) -> Option<bool> {
+        self.flags.iter().rev().find_map(|(flag, _)| {
+            if *flag == name {
+                Some(true)
+            } else if flag.strip_prefix("no-") == Some(name) {
+                Some(false)
+            } else {
+                None
             }
+        })
+    }
+
+    /// Returns the value of the last `--name=value` flag, if any.
+    pub fn value_of(&self, name: &str) -> Option<&'a str> {
+        self.flags
+            .iter()
+            .rev()
+            .find(|(flag, value)| *flag == name && value.is_some())
+            .and_then(|(_, value)| *value)
+    }
+
+    /// Iterates over the flag names and values in the order they were encountered.
+    pub fn iter(&self) -> impl Iterator<Item = (&'a str, Option<&'a str>)> + '_ {
+        self.flags.iter().copied()
+    }
+
+    /// Returns the arguments after the first `--`, as they were passed.
+    pub fn trailing_args(&self) -> &[&'a str] {
+        &self.trailing
+    }
+
+    /// Copies the flags into an owned FlagConfig, leaving out the trailing arguments.
+    pub fn to_flag_config(&self) -> FlagConfig {
+        FlagConfig(
+            self.flags
+                .iter()
+                .map(|(name, value)| flag_string(name, *value))
+                .collect(),
+        )
+    }
+}
+
+/// Finds the file descriptor passed with `--config-fd N` or `--config-fd=N`
+/// Meant for supervisors that pipe the config instead of writing it to disk; load it with `files::load_from_fd`
+/// If it's given more than once the last one wins, like `--config` in `files::path_from_args`, and arguments after `--` aren't read
+/// Holds None if the argument is missing or the last one's value isn't a valid descriptor number
+#[cfg(unix)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ConfigFdArg(pub Option<RawFd>);
+
+#[cfg(unix)]
+impl CommandlineConfig for ConfigFdArg {
+    fn from_env_args(args: Vec<String>) -> Self {
+        let (args, _) = split_trailing(&args);
+        let mut args = args.iter();
+        let mut found = None;
+
+        while let Some(arg) = args.next() {
+            let value = match arg.strip_prefix("--config-fd") {
+                Some("") => args.next().map(String::as_str),
+                Some(rest) => match rest.strip_prefix('=') {
+                    Some(value) => Some(value),
+                    None => continue,
+                },
+                None => continue,
+            };
+
+            found = Some(value.and_then(|v| v.parse().ok()).filter(|fd| *fd >= 0));
         }
 
-        Self(flags)
+        Self(found.flatten())
     }
-}
\ No newline at end of file
+}
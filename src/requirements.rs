@@ -0,0 +1,288 @@
+use serde_json::Value;
+
+use std::{
+    env, fmt,
+    fs::{self, File, OpenOptions},
+    net::{SocketAddr, TcpListener, ToSocketAddrs},
+    path::{Path, PathBuf},
+    process,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+/// The kind of check a [`Requirement`] performs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RequirementKind {
+    /// The value is a path to a file that can be opened for reading
+    FileReadable,
+    /// The value is a path to a directory that files can be created in
+    DirWritable,
+    /// The value is a port number or a `host:port` address that can be listened on
+    PortBindable,
+    /// The value is the name of an executable found on `PATH`, or a path to an executable
+    ExecutableOnPath,
+}
+
+/// An external prerequisite referenced by a config value, such as a file the config points to.
+/// Requirements are bound to the dotted path of the value in the config, like `tls.cert_path`.
+/// ### Example
+/// ```rust
+/// use rsconfig::requirements::{self, Requirement};
+///
+/// let config = serde_json::json!({
+///     "storage": { "data_dir": std::env::temp_dir() },
+///     "server": { "listen": "127.0.0.1:0" },
+/// });
+///
+/// let reqs = [
+///     Requirement::dir_writable("storage.data_dir"),
+///     Requirement::port_bindable("server.listen"),
+///     // checks can be skipped where they can't pass, like in offline CI
+///     Requirement::executable_on_path("tools.ffmpeg").skip(true),
+/// ];
+///
+/// assert!(requirements::verify(&config, &reqs).is_empty());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Requirement {
+    path: String,
+    kind: RequirementKind,
+    skipped: bool,
+}
+
+impl Requirement {
+    fn new(path: impl Into<String>, kind: RequirementKind) -> Self {
+        Self {
+            path: path.into(),
+            kind,
+            skipped: false,
+        }
+    }
+
+    /// Requires the value at `path` to be a readable file.
+    pub fn file_readable(path: impl Into<String>) -> Self {
+        Self::new(path, RequirementKind::FileReadable)
+    }
+
+    /// Requires the value at `path` to be a directory files can be created in.
+    pub fn dir_writable(path: impl Into<String>) -> Self {
+        Self::new(path, RequirementKind::DirWritable)
+    }
+
+    /// Requires the value at `path` to be a port (or `host:port` address) that can be listened on.
+    /// A bare port number is checked on all interfaces. The socket is closed as soon as the check is done.
+    pub fn port_bindable(path: impl Into<String>) -> Self {
+        Self::new(path, RequirementKind::PortBindable)
+    }
+
+    /// Requires the value at `path` to name an executable on `PATH`, or to be a path to an executable.
+    pub fn executable_on_path(path: impl Into<String>) -> Self {
+        Self::new(path, RequirementKind::ExecutableOnPath)
+    }
+
+    /// Skips this requirement when `skip` is true, for example port checks in offline CI.
+    pub fn skip(mut self, skip: bool) -> Self {
+        self.skipped = skip;
+        self
+    }
+
+    /// The dotted path of the config value this requirement checks.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// The kind of check this requirement performs.
+    pub fn kind(&self) -> RequirementKind {
+        self.kind
+    }
+}
+
+/// A requirement that wasn't met.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequirementFailure {
+    /// Dotted path of the config value that was checked
+    pub path: String,
+    /// The value found at the path, or None if it was missing
+    pub value: Option<String>,
+    /// The kind of check that failed
+    pub kind: RequirementKind,
+    /// What went wrong and how to fix it
+    pub message: String,
+}
+
+impl fmt::Display for RequirementFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.value {
+            Some(value) => write!(f, "{} = {:?}: {}", self.path, value, self.message),
+            None => write!(f, "{}: {}", self.path, self.message),
+        }
+    }
+}
+
+/// Runs every requirement that isn't skipped against a config value, returning the ones that failed in order.
+/// An empty result means every prerequisite is in place.
+/// [`ConfigBuilder::require`](crate::layers::ConfigBuilder::require) runs this on the merged layers.
+pub fn verify(config: &Value, reqs: &[Requirement]) -> Vec<RequirementFailure> {
+    reqs.iter()
+        .filter(|req| !req.skipped)
+        .filter_map(|req| check(config, req))
+        .collect()
+}
+
+fn check(config: &Value, req: &Requirement) -> Option<RequirementFailure> {
+    let failure = |value: Option<String>, message: String| RequirementFailure {
+        path: req.path.clone(),
+        value,
+        kind: req.kind,
+        message,
+    };
+
+    let value = match lookup(config, &req.path) {
+        Some(Value::String(s)) => s.clone(),
+        Some(Value::Number(n)) if req.kind == RequirementKind::PortBindable => n.to_string(),
+        Some(other) => {
+            return Some(failure(
+                Some(other.to_string()),
+                "has the wrong type, expected a string".to_string(),
+            ))
+        }
+        None => {
+            return Some(failure(
+                None,
+                "is missing from the config, set it to enable this check".to_string(),
+            ))
+        }
+    };
+
+    let result = match req.kind {
+        RequirementKind::FileReadable => check_file_readable(Path::new(&value)),
+        RequirementKind::DirWritable => check_dir_writable(Path::new(&value)),
+        RequirementKind::PortBindable => check_port_bindable(&value),
+        RequirementKind::ExecutableOnPath => check_executable(&value),
+    };
+
+    result.err().map(|message| failure(Some(value), message))
+}
+
+// follows a dotted path through objects, with numeric segments indexing arrays
+fn lookup<'a>(config: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('.')
+        .try_fold(config, |value, segment| match value {
+            Value::Object(map) => map.get(segment),
+            Value::Array(values) => values.get(segment.parse::<usize>().ok()?),
+            _ => None,
+        })
+}
+
+fn check_file_readable(path: &Path) -> Result<(), String> {
+    if path.is_dir() {
+        return Err("is a directory, point it at a file".to_string());
+    }
+
+    File::open(path).map(drop).map_err(|e| {
+        format!(
+            "can't be opened for reading ({}), check that the file exists and its permissions",
+            e
+        )
+    })
+}
+
+fn check_dir_writable(path: &Path) -> Result<(), String> {
+    if !path.exists() {
+        return Err("doesn't exist, create the directory or fix the path".to_string());
+    }
+
+    if !path.is_dir() {
+        return Err("isn't a directory, point it at a directory".to_string());
+    }
+
+    // creating a file is the only reliable check, permission bits don't account for ACLs or read-only mounts.
+    // the counter keeps concurrent checks of the same directory from colliding on the probe
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+    let probe = path.join(format!(
+        ".rsconfig-probe-{}-{}",
+        process::id(),
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+
+    match OpenOptions::new().write(true).create_new(true).open(&probe) {
+        Ok(_) => {
+            let _ = fs::remove_file(&probe);
+            Ok(())
+        }
+        Err(e) => Err(format!(
+            "isn't writable ({}), check the directory's permissions",
+            e
+        )),
+    }
+}
+
+fn check_port_bindable(value: &str) -> Result<(), String> {
+    let addrs: Vec<SocketAddr> = match value.parse::<u16>() {
+        Ok(port) => vec![SocketAddr::from(([0, 0, 0, 0], port))],
+        Err(_) => value
+            .to_socket_addrs()
+            .map_err(|e| format!("isn't a valid port or host:port address ({})", e))?
+            .collect(),
+    };
+
+    // the listener is dropped right away so the port is free for the application
+    TcpListener::bind(&addrs[..]).map(drop).map_err(|e| {
+        format!(
+            "can't be listened on ({}), stop whatever is using it or pick another port",
+            e
+        )
+    })
+}
+
+fn check_executable(value: &str) -> Result<(), String> {
+    let path = Path::new(value);
+
+    // anything with a directory part is a path rather than a name to search for
+    if path.components().count() > 1 {
+        return if is_executable(path) {
+            Ok(())
+        } else {
+            Err("isn't an executable file, check the path and its permissions".to_string())
+        };
+    }
+
+    let dirs = env::var_os("PATH").unwrap_or_default();
+
+    if env::split_paths(&dirs).any(|dir| candidates(&dir, value).iter().any(|p| is_executable(p))) {
+        Ok(())
+    } else {
+        Err("wasn't found on PATH, install it or set the full path".to_string())
+    }
+}
+
+#[cfg(not(windows))]
+fn candidates(dir: &Path, name: &str) -> Vec<PathBuf> {
+    vec![dir.join(name)]
+}
+
+// windows finds `ffmpeg` as `ffmpeg.exe` using the extensions in PATHEXT
+#[cfg(windows)]
+fn candidates(dir: &Path, name: &str) -> Vec<PathBuf> {
+    let extensions = env::var("PATHEXT").unwrap_or_else(|_| ".COM;.EXE;.BAT;.CMD".to_string());
+
+    std::iter::once(dir.join(name))
+        .chain(
+            extensions
+                .split(';')
+                .filter(|ext| !ext.is_empty())
+                .map(|ext| dir.join(format!("{}{}", name, ext))),
+        )
+        .collect()
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+
+    fs::metadata(path).is_ok_and(|meta| meta.is_file() && meta.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
@@ -0,0 +1,63 @@
+use serde_json::{Map, Value};
+use xmltree::{Element, XMLNode};
+
+/// Converts an XML element into JSON, so XmlConfig types can reuse JSON-style lookups (or a JsonConfig impl).
+/// The mapping is deterministic:
+/// - an element with only text (or nothing) becomes a string, `""` when empty
+/// - otherwise it becomes an object where
+///   - each attribute becomes an `"@name"` key with a string value
+///   - each child element becomes a key named after it; repeated children become an array in document order
+///   - non-empty text becomes a `"#text"` key
+///
+/// Text is trimmed, values are never converted to numbers or booleans, and comments are ignored.
+/// The element's own name isn't included.
+/// ### Example
+/// ```rust
+/// let root = xmltree::Element::parse(
+///     r#"<config debug="true"><host>a</host><host>b</host><port>80</port></config>"#.as_bytes(),
+/// ).unwrap();
+///
+/// assert_eq!(
+///     rsconfig::xml::to_json(&root),
+///     serde_json::json!({"@debug": "true", "host": ["a", "b"], "port": "80"}),
+/// );
+/// ```
+pub fn to_json(element: &Element) -> Value {
+    let text = element
+        .get_text()
+        .map(|text| text.trim().to_string())
+        .unwrap_or_default();
+
+    let has_children = element
+        .children
+        .iter()
+        .any(|child| matches!(child, XMLNode::Element(_)));
+
+    if element.attributes.is_empty() && !has_children {
+        return Value::String(text);
+    }
+
+    let mut map = Map::new();
+
+    for (name, value) in &element.attributes {
+        map.insert(format!("@{}", name), Value::String(value.clone()));
+    }
+
+    for child in element.children.iter().filter_map(XMLNode::as_element) {
+        let value = to_json(child);
+
+        match map.get_mut(&child.name) {
+            Some(Value::Array(values)) => values.push(value),
+            Some(existing) => *existing = Value::Array(vec![existing.take(), value]),
+            None => {
+                map.insert(child.name.clone(), value);
+            }
+        }
+    }
+
+    if !text.is_empty() {
+        map.insert("#text".to_string(), Value::String(text));
+    }
+
+    Value::Object(map)
+}